@@ -1,34 +1,108 @@
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
 use tracing::{event, Level};
-use tracing_subscriber;
 
+use std::path::Path;
 use std::time::Instant;
 
 use propensity_score::prelude::*;
 
-const FILENAME: &str = "/Users/edmund/Downloads/creditcard.v2.csv";
+const USAGE: &str =
+    "usage: propensity_bin <csv> [--headers] [--max-iters N (100)] [--format human|json]";
+
+/// How the findings are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    /// the report and a sample of the predictions
+    Human,
+    /// `Findings::to_json`, for piping into other tools
+    Json,
+}
+
+#[derive(Debug)]
+struct Args {
+    filename: String,
+    with_headers: bool,
+    max_iters: u64,
+    format: Format,
+}
+
+impl Args {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args> {
+        let mut filename = None;
+        let mut with_headers = false;
+        let mut max_iters = 100;
+        let mut format = Format::Human;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headers" => with_headers = true,
+                "--max-iters" => {
+                    max_iters = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| eyre!("--max-iters takes a whole number\n{}", USAGE))?
+                }
+                "--format" => {
+                    format = match args.next().as_deref() {
+                        Some("human") => Format::Human,
+                        Some("json") => Format::Json,
+                        other => {
+                            return Err(eyre!(
+                                "--format takes human or json, got {:?}\n{}",
+                                other,
+                                USAGE
+                            ))
+                        }
+                    }
+                }
+                _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+                _ => return Err(eyre!("Unexpected argument '{}'\n{}", arg, USAGE)),
+            }
+        }
+        let filename = filename.ok_or_else(|| eyre!("Missing the csv to fit\n{}", USAGE))?;
+        if !Path::new(&filename).is_file() {
+            return Err(eyre!("No csv at '{}'\n{}", filename, USAGE));
+        }
+        Ok(Args {
+            filename,
+            with_headers,
+            max_iters,
+            format,
+        })
+    }
+}
 
 /// Called by some internal process that knows to put the target
 /// data in the first column. The data is a "dense matrix".  A single array of floats.
 fn main() -> Result<()> {
-    // performance and debugging metrics
-    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+    // performance and debugging metrics; on stderr so stdout holds only the findings
+    tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .with_writer(std::io::stderr)
+        .init();
+    let args = Args::parse(std::env::args().skip(1))?;
     let start = Instant::now();
 
     // build the objective
     // memory is allocated when from file
-    let objective = Objective::from_csv(FILENAME, false)?;
+    let objective = Objective::from_csv(&args.filename, args.with_headers)?;
 
-    let cfg = CfgBuilder::new().max_iters(100).logging(false).build();
+    let cfg = CfgBuilder::new()
+        .max_iters(args.max_iters)
+        .logging(false)
+        .build();
 
     let findings = logit::run(&objective, cfg)?;
 
     let duration = start.elapsed();
 
-    findings.report()?;
-
-    let y_hat = findings.predict(false); // binary = false, show_sample
-    y_hat.show(5);
+    match args.format {
+        Format::Human => {
+            findings.write_report(&mut std::io::stdout())?;
+            let y_hat = findings.predict(false); // binary = false, show_sample
+            y_hat.show(5);
+        }
+        Format::Json => println!("{}", findings.to_json()?),
+    }
 
     event!(
         Level::INFO,
@@ -38,3 +112,56 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The arguments after the program name, with an existing csv first
+    fn parse(flags: &[&str]) -> Result<Args> {
+        let csv = std::env::temp_dir().join(format!("propensity-bin-{}.csv", std::process::id()));
+        std::fs::write(&csv, "0,1\n1,2\n").unwrap();
+        let args = std::iter::once(csv.display().to_string());
+        Args::parse(args.chain(flags.iter().map(|flag| flag.to_string())))
+    }
+
+    #[test]
+    fn format_defaults_to_human_and_takes_json() {
+        assert_eq!(parse(&[]).unwrap().format, Format::Human);
+        assert_eq!(parse(&["--format", "json"]).unwrap().format, Format::Json);
+        assert_eq!(parse(&["--format", "human"]).unwrap().format, Format::Human);
+        let err = parse(&["--format", "xml"]).unwrap_err().to_string();
+        assert!(
+            err.starts_with("--format takes human or json, got Some(\"xml\")"),
+            "{err}"
+        );
+        assert!(parse(&["--format"]).is_err());
+    }
+
+    #[test]
+    fn the_csv_headers_and_max_iters_come_from_the_arguments() {
+        let args = parse(&["--headers", "--max-iters", "250"]).unwrap();
+        assert!(args.with_headers);
+        assert_eq!(args.max_iters, 250);
+        assert!(args.filename.ends_with(".csv"));
+
+        let defaults = parse(&[]).unwrap();
+        assert!(!defaults.with_headers);
+        assert_eq!(defaults.max_iters, 100);
+
+        let err = parse(&["--max-iters", "many"]).unwrap_err().to_string();
+        assert!(err.starts_with("--max-iters takes a whole number"), "{err}");
+        let err = parse(&["other.csv"]).unwrap_err().to_string();
+        assert!(err.starts_with("Unexpected argument 'other.csv'"), "{err}");
+    }
+
+    #[test]
+    fn the_csv_must_be_given_and_exist() {
+        let err = Args::parse(std::iter::empty()).unwrap_err().to_string();
+        assert!(err.starts_with("Missing the csv to fit"), "{err}");
+        let err = Args::parse(std::iter::once("/no/such.csv".to_string()))
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("No csv at '/no/such.csv'"), "{err}");
+    }
+}
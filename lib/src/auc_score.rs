@@ -6,12 +6,19 @@ use std::iter::zip;
 use std::ops::AddAssign;
 use tracing::{event, Level};
 
+use crate::models::check_binary;
+
 // assess if the value is zero
 #[derive(Debug)]
 struct Count<T> {
     neg: T,
     pos: T,
 }
+///
+/// Area under the ROC curve of the scores `y_hat` (probabilities, log-odds or
+/// anything that ranks) against the 0/1 labels `y_true`; the probability that a
+/// random positive outranks a random negative.
+///
 pub fn auc_score<T>(y_true: &DVector<T>, y_hat: &DVector<T>) -> Result<f64>
 where
     T: Float + AddAssign + Display,
@@ -25,44 +32,313 @@ where
     };
 
     let mut warn_msg: &str = "";
-    y_true.iter().fold(&mut counts, |acc, yi| {
+    for yi in y_true.iter() {
         if yi == &T::zero() {
-            acc.neg += T::one();
+            counts.neg += T::one();
         } else if yi == &T::one() {
-            acc.pos += T::one();
+            counts.pos += T::one();
         } else if yi > &T::one() {
-            acc.pos += T::one();
+            counts.pos += T::one();
             warn_msg = "🟡 Found at least one non-binary value.";
         } else {
-            panic!(
+            return Err(eyre!(
                 "binary quality score (auc): only for binary classification. Invalid label: {}",
                 yi
-            );
+            ));
         }
-        acc
-    });
+    }
 
     if !warn_msg.is_empty() {
         event!(Level::INFO, "{}", &warn_msg);
     }
 
-    let auc: usize = zip(y_true.iter(), y_hat.iter())
-        .filter(|(yi_true, yi_hat)| yi_true == yi_hat)
-        .count();
+    if y_true.len() != y_hat.len() {
+        return Err(eyre!(
+            "Got {} scores for {} labels",
+            y_hat.len(),
+            y_true.len()
+        ));
+    }
 
-    let pos = counts
-        .pos
-        .to_f64()
-        .take()
-        .ok_or(eyre!("Failed to count 1"))?;
-    let neg = counts
-        .neg
-        .to_f64()
-        .take()
-        .ok_or(eyre!("Failed to count 0"))?;
+    let pos = counts.pos.to_f64().ok_or(eyre!("Failed to count 1"))?;
+    let neg = counts.neg.to_f64().ok_or(eyre!("Failed to count 0"))?;
+    if pos == 0.0 || neg == 0.0 {
+        return Err(eyre!(
+            "binary quality score (auc): needs both classes, got {} positive and {} negative",
+            pos,
+            neg
+        ));
+    }
+
+    // Mann-Whitney: the rank sum of the positive scores, ties sharing their mid rank
+    let mut order: Vec<(f64, bool)> = zip(y_hat.iter(), y_true.iter())
+        .map(|(score, yi)| (score.to_f64().unwrap_or(f64::NAN), *yi != T::zero()))
+        .collect();
+    check_scores(order.iter().map(|v| v.0))?;
+    order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut rank_sum = 0f64;
+    let mut start = 0;
+    while start < order.len() {
+        let end = tie_group_end(&order, start, |v| v.0);
+        // ranks start + 1 ..= end
+        let mid_rank = (start + 1 + end) as f64 / 2.0;
+        let positives = order[start..end].iter().filter(|v| v.1).count();
+        rank_sum += mid_rank * positives as f64;
+        start = end;
+    }
 
-    // let result = (auc as f64 - (pos * (pos + 1f64) / 2f64)) / (pos * neg);
-    let result = (auc as f64) / (pos + neg);
+    let result = (rank_sum - (pos * (pos + 1f64) / 2f64)) / (pos * neg);
 
     Ok(result)
 }
+
+///
+/// `auc_score` with a weight per record: the weighted probability that a
+/// positive outranks a negative, ties counting half. A weight of 2 counts like
+/// the record appearing twice; with every weight 1 it is the plain AUC.
+///
+pub fn weighted_auc(
+    y_true: &DVector<f64>,
+    y_prob: &DVector<f64>,
+    weights: &DVector<f64>,
+) -> Result<f64> {
+    if y_true.len() != y_prob.len() || y_true.len() != weights.len() {
+        return Err(eyre!(
+            "Got {} scores and {} weights for {} labels",
+            y_prob.len(),
+            weights.len(),
+            y_true.len()
+        ));
+    }
+    check_binary(y_true)?;
+    if let Some(w) = weights.iter().find(|w| !w.is_finite() || **w < 0.0) {
+        return Err(eyre!(
+            "Weights must be finite and non-negative; found {}",
+            w
+        ));
+    }
+
+    check_scores(y_prob.iter().copied())?;
+
+    let mut order: Vec<(f64, bool, f64)> = (0..y_true.len())
+        .map(|i| (y_prob[i], y_true[i] == 1.0, weights[i]))
+        .collect();
+    order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // each tie group's positives outrank the negatives below it and tie with its own
+    let (mut area, mut neg_below, mut pos_total) = (0f64, 0f64, 0f64);
+    let mut start = 0;
+    while start < order.len() {
+        let end = tie_group_end(&order, start, |v| v.0);
+        let (pos, neg) = order[start..end].iter().fold((0.0, 0.0), |(pos, neg), v| {
+            if v.1 {
+                (pos + v.2, neg)
+            } else {
+                (pos, neg + v.2)
+            }
+        });
+        area += pos * (neg_below + neg / 2.0);
+        neg_below += neg;
+        pos_total += pos;
+        start = end;
+    }
+
+    if pos_total == 0.0 || neg_below == 0.0 {
+        return Err(eyre!(
+            "binary quality score (auc): needs weight on both classes, got {} positive and {} negative",
+            pos_total,
+            neg_below
+        ));
+    }
+    Ok(area / (pos_total * neg_below))
+}
+
+///
+/// `auc_score` in `O(n + bins)` time and `O(bins)` memory for probabilities in
+/// `[0, 1]`: the scores are histogrammed into `bins` equal-width bins per class
+/// and records sharing a bin count as ties. Only positive/negative pairs within
+/// one bin can be misordered, so the error is at most half the share of such
+/// pairs; it vanishes as `bins` grows (1,000 bins is typically within 1e-3).
+/// See `BinnedAuc` to feed scores as they stream in.
+///
+pub fn auc_score_binned(y_true: &DVector<f64>, y_prob: &DVector<f64>, bins: usize) -> Result<f64> {
+    if y_true.len() != y_prob.len() {
+        return Err(eyre!(
+            "Got {} scores for {} labels",
+            y_prob.len(),
+            y_true.len()
+        ));
+    }
+    let mut auc = BinnedAuc::new(bins)?;
+    for (label, prob) in zip(y_true.iter(), y_prob.iter()) {
+        auc.push(*label, *prob)?;
+    }
+    auc.auc()
+}
+
+///
+/// The running histograms behind `auc_score_binned`; `push` each record, then
+/// read the `auc` at any point.
+///
+#[derive(Debug, Clone)]
+pub struct BinnedAuc {
+    pos: Vec<f64>,
+    neg: Vec<f64>,
+}
+
+impl BinnedAuc {
+    pub fn new(bins: usize) -> Result<Self> {
+        if bins == 0 {
+            return Err(eyre!("The binned auc needs at least 1 bin"));
+        }
+        Ok(BinnedAuc {
+            pos: vec![0.0; bins],
+            neg: vec![0.0; bins],
+        })
+    }
+    /// Count a record with 0/1 `label` and probability `prob`
+    pub fn push(&mut self, label: f64, prob: f64) -> Result<()> {
+        if !(0.0..=1.0).contains(&prob) {
+            return Err(eyre!(
+                "The binned auc takes probabilities in [0, 1], got {}",
+                prob
+            ));
+        }
+        let bins = self.pos.len();
+        let bin = ((prob * bins as f64) as usize).min(bins - 1);
+        if label == 1.0 {
+            self.pos[bin] += 1.0;
+        } else if label == 0.0 {
+            self.neg[bin] += 1.0;
+        } else {
+            return Err(eyre!("The target must be binary (0 or 1); found {}", label));
+        }
+        Ok(())
+    }
+    /// The AUC of the records pushed so far
+    pub fn auc(&self) -> Result<f64> {
+        // each bin's positives outrank the negatives below it and tie with its own
+        let (mut area, mut neg_below, mut pos_total) = (0f64, 0f64, 0f64);
+        for (pos, neg) in zip(self.pos.iter(), self.neg.iter()) {
+            area += pos * (neg_below + neg / 2.0);
+            neg_below += neg;
+            pos_total += pos;
+        }
+        if pos_total == 0.0 || neg_below == 0.0 {
+            return Err(eyre!(
+                "binary quality score (auc): needs both classes, got {} positive and {} negative",
+                pos_total,
+                neg_below
+            ));
+        }
+        Ok(area / (pos_total * neg_below))
+    }
+}
+
+/// NaN scores can't be ranked (and would never leave their tie group)
+fn check_scores(mut scores: impl Iterator<Item = f64>) -> Result<()> {
+    match scores.find(|v| !v.is_finite()) {
+        None => Ok(()),
+        Some(v) => Err(eyre!(
+            "binary quality score (auc): scores must be finite; found {}",
+            v
+        )),
+    }
+}
+
+/// One past the last record of the tie group at `start` of the sorted `order`
+fn tie_group_end<R>(order: &[R], start: usize, score: impl Fn(&R) -> f64) -> usize {
+    let first = score(&order[start]);
+    start
+        + 1
+        + order[start + 1..]
+            .iter()
+            .take_while(|v| score(v) == first)
+            .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auc_score_counts_ties_as_half() {
+        let y = DVector::from_vec(vec![0.0, 0.0, 1.0, 1.0]);
+        let p = DVector::from_vec(vec![0.1, 0.4, 0.35, 0.8]);
+        assert_eq!(auc_score(&y, &p).unwrap(), 0.75);
+        let tied = DVector::from_vec(vec![0.5, 0.5, 0.5, 0.5]);
+        assert_eq!(auc_score(&y, &tied).unwrap(), 0.5);
+    }
+
+    #[test]
+    fn auc_score_rejects_a_nan_score() {
+        let y = DVector::from_vec(vec![0.0, 1.0, 0.0, 1.0]);
+        let p = DVector::from_vec(vec![0.2, f64::NAN, 0.4, 0.9]);
+        assert!(auc_score(&y, &p).is_err());
+        let p = DVector::from_vec(vec![0.2, f64::INFINITY, 0.4, 0.9]);
+        assert!(auc_score(&y, &p).is_err());
+    }
+
+    #[test]
+    fn weighted_auc_with_unit_weights_is_the_auc() {
+        let y = DVector::from_vec(vec![0.0, 0.0, 1.0, 1.0]);
+        let p = DVector::from_vec(vec![0.1, 0.4, 0.35, 0.8]);
+        let ones = DVector::from_element(4, 1.0);
+        assert_eq!(weighted_auc(&y, &p, &ones).unwrap(), 0.75);
+        // doubling the misranked negative is the same as listing it twice
+        let w = DVector::from_vec(vec![1.0, 2.0, 1.0, 1.0]);
+        let y2 = DVector::from_vec(vec![0.0, 0.0, 0.0, 1.0, 1.0]);
+        let p2 = DVector::from_vec(vec![0.1, 0.4, 0.4, 0.35, 0.8]);
+        assert_eq!(
+            weighted_auc(&y, &p, &w).unwrap(),
+            auc_score(&y2, &p2).unwrap()
+        );
+    }
+
+    #[test]
+    fn weighted_auc_rejects_a_nan_score() {
+        let y = DVector::from_vec(vec![0.0, 1.0, 0.0, 1.0]);
+        let p = DVector::from_vec(vec![0.2, f64::NAN, 0.4, 0.9]);
+        let w = DVector::from_element(4, 1.0);
+        assert!(weighted_auc(&y, &p, &w).is_err());
+    }
+
+    #[test]
+    fn auc_score_binned_matches_the_exact_auc_when_no_bin_mixes_classes() {
+        let y = DVector::from_vec(vec![0.0, 0.0, 1.0, 1.0]);
+        let p = DVector::from_vec(vec![0.1, 0.4, 0.35, 0.8]);
+        assert_eq!(auc_score_binned(&y, &p, 10).unwrap(), 0.75);
+        // one bin ties every pair
+        assert_eq!(auc_score_binned(&y, &p, 1).unwrap(), 0.5);
+        // 0.35 and 0.4 share the bin [0.25, 0.5): that pair counts half
+        assert_eq!(auc_score_binned(&y, &p, 4).unwrap(), 0.875);
+    }
+
+    #[test]
+    fn binned_auc_streams_to_the_same_value_and_checks_its_input() {
+        let mut streaming = BinnedAuc::new(100).unwrap();
+        let (y, p): (Vec<f64>, Vec<f64>) = (0..200)
+            .map(|i| ((i % 3 == 0) as u8 as f64, ((i * 37) % 200) as f64 / 200.0))
+            .unzip();
+        for (label, prob) in y.iter().zip(&p) {
+            streaming.push(*label, *prob).unwrap();
+        }
+        let (y, p) = (DVector::from_vec(y), DVector::from_vec(p));
+        assert_eq!(
+            streaming.auc().unwrap(),
+            auc_score_binned(&y, &p, 100).unwrap()
+        );
+        assert!((streaming.auc().unwrap() - auc_score(&y, &p).unwrap()).abs() < 1e-2);
+
+        assert!(BinnedAuc::new(0).is_err());
+        assert!(streaming.push(1.0, 1.5).is_err());
+        assert!(streaming.push(2.0, 0.5).is_err());
+        assert!(BinnedAuc::new(3).unwrap().auc().is_err());
+        // a probability of 1 lands in the last bin
+        let mut edge = BinnedAuc::new(2).unwrap();
+        edge.push(1.0, 1.0).unwrap();
+        edge.push(0.0, 0.0).unwrap();
+        assert_eq!(edge.auc().unwrap(), 1.0);
+    }
+}
@@ -0,0 +1,121 @@
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+
+use crate::configurations::Cfg;
+use crate::logit;
+use crate::metrics::LOG_LOSS_EPS;
+use crate::models::{check_binary, sigmoid, Objective};
+
+///
+/// Platt scaling: `sigmoid(slope * z + intercept)` where `z` is a model's
+/// log-odds. The two parameters are a 1-D logistic regression of the true
+/// labels on `z`, fit with the same solver as the model.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibrator {
+    pub slope: f64,
+    pub intercept: f64,
+}
+
+impl Calibrator {
+    /// A calibrator from stored parameters; the inverse of `params`
+    pub fn new(slope: f64, intercept: f64) -> Self {
+        Calibrator { slope, intercept }
+    }
+    ///
+    /// Fit on held out log-odds (e.g. `Findings::decision_function`) and their
+    /// 0/1 labels
+    ///
+    pub fn fit(log_odds: &DVector<f64>, y: &DVector<f64>) -> Result<Self> {
+        if log_odds.len() != y.len() {
+            return Err(eyre!(
+                "Got {} log-odds for {} labels",
+                log_odds.len(),
+                y.len()
+            ));
+        }
+        check_binary(y)?;
+
+        let x = DMatrix::from_fn(y.len(), 2, |i, j| if j == 0 { log_odds[i] } else { 1.0 });
+        let objective = Objective::new(x, y.clone());
+        let findings = logit::run(&objective, Cfg::default())?;
+
+        Ok(Calibrator::new(
+            findings.coefficients[0],
+            findings.intercept,
+        ))
+    }
+    /// `(slope, intercept)`
+    pub fn params(&self) -> (f64, f64) {
+        (self.slope, self.intercept)
+    }
+    /// Calibrated probability from the model's log-odds
+    pub fn calibrate(&self, log_odds: f64) -> f64 {
+        sigmoid(self.slope * log_odds + self.intercept)
+    }
+    /// Calibrated probability from the model's probability; clamped away from
+    /// 0 and 1, as `log_loss` does, so that the log-odds stay finite
+    pub fn calibrate_probability(&self, probability: f64) -> f64 {
+        let p = probability.clamp(LOG_LOSS_EPS, 1.0 - LOG_LOSS_EPS);
+        self.calibrate((p / (1.0 - p)).ln())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::{run, tests::toy};
+
+    #[test]
+    fn calibrating_a_fit_on_its_own_log_odds_is_the_identity() {
+        let objective = toy();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let calibrator = Calibrator::fit(&findings.decision_function(), &objective.y).unwrap();
+        assert!((calibrator.slope - 1.0).abs() < 1e-4);
+        assert!(calibrator.intercept.abs() < 1e-4);
+    }
+
+    #[test]
+    fn calibration_lowers_the_brier_score_of_an_overconfident_model() {
+        use crate::metrics::brier_score;
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        // the model reports 3z + 1 where the true log-odds are z
+        let mut rng = StdRng::seed_from_u64(9);
+        let z = DVector::from_fn(400, |i, _| i as f64 / 100.0 - 2.0);
+        let y = z.map(|z| {
+            if rng.gen::<f64>() < sigmoid(z) {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        let reported = z.map(|z| 3.0 * z + 1.0);
+        let calibrator = Calibrator::fit(&reported, &y).unwrap();
+        assert!(calibrator.slope < 1.0);
+
+        let before = brier_score(&y, &reported.map(sigmoid)).unwrap();
+        let after = brier_score(&y, &reported.map(|z| calibrator.calibrate(z))).unwrap();
+        assert!(after < before, "{} vs {}", after, before);
+    }
+
+    #[test]
+    fn calibrate_probability_stays_finite_at_0_and_1() {
+        let calibrator = Calibrator::new(0.5, 0.25);
+        let at = |p: f64| calibrator.calibrate_probability(p);
+        assert!((at(0.5) - sigmoid(0.25)).abs() < 1e-12);
+        assert!(at(0.0).is_finite() && at(0.0) > 0.0);
+        assert!(at(1.0).is_finite() && at(1.0) < 1.0);
+        // ln(1e-15 / (1 - 1e-15)), the log-odds of the clamped 0
+        assert!((at(0.0) - sigmoid(0.5 * -34.538_776_394_910_684 + 0.25)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fit_rejects_mismatched_or_non_binary_labels() {
+        let z = DVector::from_vec(vec![0.1, -0.2]);
+        assert!(Calibrator::fit(&z, &DVector::from_vec(vec![1.0])).is_err());
+        assert!(Calibrator::fit(&z, &DVector::from_vec(vec![1.0, 3.0])).is_err());
+    }
+}
@@ -1,18 +1,56 @@
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::DVector;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::link::Link;
+
 ///
 /// Configuration for the optimization process
 ///
 pub struct CfgBuilder {
     max_iters: u64,
+    max_duration: Option<Duration>,
     logging: bool,
+    #[cfg(feature = "progress")]
+    progress: bool,
+    seed: Option<u64>,
+    on_iteration: Option<IterationCallback>,
     cfg_predict: Option<CfgPredict>,
+    solver: SolverKind,
+    l2: f64,
+    l1: f64,
+    gtol: f64,
+    link: Link,
+    warm_start: Option<DVector<f64>>,
 }
 
+/// The gradient norm below which the fit has converged, argmin's LBFGS default
+const DEFAULT_GTOL: f64 = 1.490_116_119_384_765_6e-8;
+
 impl CfgBuilder {
     pub fn new() -> CfgBuilder {
         CfgBuilder {
             max_iters: 100,
+            max_duration: None,
             logging: false,
+            #[cfg(feature = "progress")]
+            progress: false,
+            seed: None,
+            on_iteration: None,
             cfg_predict: None,
+            solver: SolverKind::default(),
+            l2: 0.0,
+            l1: 0.0,
+            gtol: DEFAULT_GTOL,
+            link: Link::default(),
+            warm_start: None,
         }
     }
 
@@ -21,11 +59,87 @@ impl CfgBuilder {
         self
     }
 
+    /// Wall-clock budget for the fit; `run` returns the best betas found so far
+    /// when it runs out, with `Findings::timed_out` set. `max_iters` still applies.
+    pub fn max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
     pub fn logging(mut self, logging: bool) -> Self {
         self.logging = logging;
         self
     }
 
+    /// Draw a progress bar over `max_iters` in the terminal (LBFGS fits)
+    #[cfg(feature = "progress")]
+    pub fn progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    ///
+    /// Seeds every randomized step: the sgd batch order,
+    /// `Objective::stratified_split` and `metrics::auc_bootstrap_ci`. Two runs
+    /// with the same seed follow the same trajectory bit for bit. Without a seed
+    /// each run draws fresh entropy. Recorded with the findings.
+    ///
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Called with the iteration number and cost after every iteration; runs
+    /// alongside the `logging` observer
+    pub fn on_iteration<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(u64, f64) + Send + 'static,
+    {
+        self.on_iteration = Some(IterationCallback(Arc::new(Mutex::new(callback))));
+        self
+    }
+
+    pub fn solver(mut self, solver: SolverKind) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    /// Ridge penalty `l2 / 2 * |coefficients|²` added to the cost; the intercept
+    /// is not penalized. 0 (the default) is the plain maximum likelihood fit.
+    pub fn l2(mut self, l2: f64) -> Self {
+        self.l2 = l2;
+        self
+    }
+
+    /// Lasso penalty `l1 * |w|_1` added to the cost, which drives weak
+    /// coefficients to exactly 0. Not differentiable at 0, so only
+    /// `SolverKind::OwlQn` can fit it; `run` rejects it with any other solver.
+    pub fn l1(mut self, l1: f64) -> Self {
+        self.l1 = l1;
+        self
+    }
+
+    /// Stop once the norm of the cost's gradient falls below `gtol`, the first
+    /// order optimality condition; checked every iteration (every epoch for sgd).
+    /// The cost sums over the records, so its gradient grows with their count.
+    pub fn gtol(mut self, gtol: f64) -> Self {
+        self.gtol = gtol;
+        self
+    }
+
+    /// The link from the linear predictor to the probability; `Logit` by default
+    pub fn link(mut self, link: Link) -> Self {
+        self.link = link;
+        self
+    }
+
+    /// Start the solver from these betas (intercept last) rather than zeros,
+    /// e.g. a previous fit's `all_betas`
+    pub fn warm_start(mut self, betas: DVector<f64>) -> Self {
+        self.warm_start = Some(betas);
+        self
+    }
+
     pub fn with_predict(mut self, cfg_predict: CfgPredict) -> Self {
         self.cfg_predict = Some(cfg_predict);
         self
@@ -34,20 +148,148 @@ impl CfgBuilder {
     pub fn build(self) -> Cfg {
         Cfg {
             max_iters: self.max_iters,
+            max_duration: self.max_duration,
             logging: self.logging,
+            #[cfg(feature = "progress")]
+            progress: self.progress,
+            seed: self.seed,
+            on_iteration: self.on_iteration,
             cfg_predict: self.cfg_predict,
+            solver: self.solver,
+            l2: self.l2,
+            l1: self.l1,
+            gtol: self.gtol,
+            link: self.link,
+            warm_start: self.warm_start,
         }
     }
 }
 
+///
+/// With the `serde` feature a `Cfg` round trips through toml (or json); fields
+/// left out take their default. `on_iteration` and `warm_start` belong to a
+/// single run and are never stored.
+///
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
 pub struct Cfg {
     pub max_iters: u64,
+    pub max_duration: Option<Duration>,
     pub logging: bool,
+    #[cfg(feature = "progress")]
+    pub progress: bool,
+    pub seed: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub on_iteration: Option<IterationCallback>,
     pub cfg_predict: Option<CfgPredict>,
+    pub solver: SolverKind,
+    pub l2: f64,
+    pub l1: f64,
+    pub gtol: f64,
+    pub link: Link,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub warm_start: Option<DVector<f64>>,
 }
 
+///
+/// How `logit::run` searches for the betas
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SolverKind {
+    /// quasi-Newton with a backtracking line search
+    #[default]
+    Lbfgs,
+    /// Newton-Raphson (equivalently IRLS) on the exact Hessian `X' W X`; converges
+    /// in a handful of iterations on well-conditioned problems. Dense designs only.
+    Newton,
+    /// orthant-wise LBFGS (OWL-QN), for the non-smooth `CfgBuilder::l1` penalty;
+    /// it searches the slopes only, solving for the intercept at each step, so
+    /// the intercept is left unpenalized as with `CfgBuilder::l2`
+    OwlQn,
+    /// mini-batch stochastic gradient descent for data too large for full-batch
+    /// steps; trades exactness for scalability. `max_iters` is ignored in favour
+    /// of `epochs`, and the batches are shuffled with `CfgBuilder::seed`.
+    Sgd {
+        batch_size: usize,
+        learning_rate: f64,
+        epochs: u64,
+        /// share of the previous step carried into the next; 0 for plain sgd
+        momentum: f64,
+    },
+}
+
+///
+/// User supplied `(iteration, cost)` observer; see `CfgBuilder::on_iteration`
+///
+#[derive(Clone)]
+pub struct IterationCallback(pub(crate) Arc<Mutex<dyn FnMut(u64, f64) + Send>>);
+
+impl fmt::Debug for IterationCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IterationCallback")
+    }
+}
+
+///
+/// The prediction `logit::run` attaches to `Findings::default_prediction`
+///
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
 pub struct CfgPredict {
+    /// 0/1 labels rather than probabilities
     pub binary_output: bool,
+    /// probabilities above this are labelled 1
+    pub threshold: f64,
+}
+
+impl CfgPredict {
+    pub fn new() -> Self {
+        CfgPredict {
+            binary_output: false,
+            threshold: 0.5,
+        }
+    }
+    pub fn binary_output(mut self, binary_output: bool) -> Self {
+        self.binary_output = binary_output;
+        self
+    }
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl Default for CfgPredict {
+    fn default() -> Self {
+        CfgPredict::new()
+    }
+}
+
+///
+/// What to include in `Findings::report`
+///
+#[derive(Debug, Clone)]
+pub struct CfgReport {
+    /// print the confidence interval of each coefficient at this level
+    pub confidence_level: Option<f64>,
+    /// add a block with the average marginal effect of each predictor
+    pub marginal_effects: bool,
+}
+
+impl Default for CfgReport {
+    fn default() -> Self {
+        CfgReport {
+            confidence_level: Some(0.95),
+            marginal_effects: false,
+        }
+    }
+}
+
+impl Default for CfgBuilder {
+    fn default() -> Self {
+        CfgBuilder::new()
+    }
 }
 
 impl Default for Cfg {
@@ -56,10 +298,103 @@ impl Default for Cfg {
     }
 }
 
-impl Default for CfgPredict {
-    fn default() -> Self {
-        CfgPredict {
-            binary_output: false,
+impl Cfg {
+    /// The penalties are in range and the solver can fit them
+    pub(crate) fn check_penalties(&self) -> Result<()> {
+        if !(self.l1.is_finite() && self.l1 >= 0.0) {
+            return Err(eyre!(
+                "The l1 penalty must be finite and >= 0; got {}",
+                self.l1
+            ));
+        }
+        if self.l1 > 0.0 && self.solver != SolverKind::OwlQn {
+            return Err(eyre!(
+                "{:?} can't fit an l1 penalty, which isn't smooth at 0; use SolverKind::OwlQn",
+                self.solver
+            ));
         }
+        Ok(())
+    }
+    /// The generator behind the fit's randomness; seeded by `seed`, else by entropy
+    pub(crate) fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Cfg {
+    /// Read a `Cfg` stored as toml, e.g. `max_iters = 200` and `[solver.Sgd]`
+    pub fn from_toml_path<P: AsRef<std::path::Path>>(path: P) -> Result<Cfg> {
+        let text = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| eyre!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        toml::from_str(&text).map_err(|e| eyre!("Invalid cfg toml: {}", e))
+    }
+    /// The toml `from_toml_path` reads back
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| eyre!("Failed to write the cfg as toml: {}", e))
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::logit::tests::toy;
+
+    #[test]
+    fn cfg_toml_fills_missing_fields_with_defaults() {
+        let path = std::env::temp_dir().join(format!("propensity-cfg-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "max_iters = 200\nl2 = 0.5\n\n[solver.Sgd]\nbatch_size = 8\nlearning_rate = 0.1\nepochs = 3\nmomentum = 0.9\n",
+        )
+        .unwrap();
+        let cfg = Cfg::from_toml_path(&path).unwrap();
+        let defaults = Cfg::default();
+        assert_eq!(cfg.max_iters, 200);
+        assert_eq!(cfg.l2, 0.5);
+        assert_eq!(
+            cfg.solver,
+            SolverKind::Sgd {
+                batch_size: 8,
+                learning_rate: 0.1,
+                epochs: 3,
+                momentum: 0.9
+            }
+        );
+        assert_eq!(cfg.l1, defaults.l1);
+        assert_eq!(cfg.gtol, defaults.gtol);
+        assert_eq!(cfg.seed, None);
+        assert!(cfg.cfg_predict.is_none());
+    }
+
+    #[test]
+    fn cfg_round_trips_through_toml_without_its_run_state() {
+        let cfg = || {
+            CfgBuilder::new()
+                .max_iters(42)
+                .seed(7)
+                .l2(0.5)
+                .with_predict(CfgPredict::new().threshold(0.3))
+        };
+        let text = cfg().build().to_toml_string().unwrap();
+        let back: Cfg = toml::from_str(&text).unwrap();
+        assert_eq!(back.max_iters, 42);
+        assert_eq!(back.seed, Some(7));
+        assert_eq!(back.cfg_predict.as_ref().unwrap().threshold, 0.3);
+        assert_eq!(back.solver, SolverKind::Lbfgs);
+        // the rebuilt cfg fits the same model
+        let objective = toy();
+        let original = objective.fit(cfg().build()).unwrap();
+        assert_eq!(objective.fit(back).unwrap().all_betas, original.all_betas);
+
+        // a warm start is the state of one run, not configuration
+        let warm = cfg().warm_start(DVector::from_vec(vec![1.0, 2.0])).build();
+        let back: Cfg = toml::from_str(&warm.to_toml_string().unwrap()).unwrap();
+        assert!(back.warm_start.is_none());
+
+        assert!(Cfg::from_toml_path("/no/such/cfg.toml").is_err());
     }
 }
@@ -0,0 +1,752 @@
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+
+use crate::link::Link;
+use crate::models::{Findings, Objective};
+use crate::stats::{normal_ppf, two_sided_pvalue};
+
+///
+/// Inference on the fitted betas. Everything here is derived from the observed
+/// Fisher information at the optimum, so it is only as trustworthy as the fit.
+///
+impl Findings<'_> {
+    ///
+    /// Observed Fisher information `X' W X` with `W = diag(p * (1 - p))` at the
+    /// fitted betas; the Hessian of the negative log-likelihood. `p x p` where p
+    /// counts the intercept (last row and column). Under the probit link it is
+    /// the expected information, `W = diag(phi² / (p (1 - p)))`.
+    ///
+    pub fn hessian(&self) -> DMatrix<f64> {
+        information_matrix(
+            &self.objective.x,
+            &self.objective.log_odds(&self.all_betas),
+            self.objective.weights.as_ref(),
+            self.cfg.link,
+        )
+    }
+
+    ///
+    /// Covariance of all_betas (intercept in the last slot); the inverse of the
+    /// information matrix.
+    ///
+    pub fn covariance(&self) -> Result<DMatrix<f64>> {
+        self.hessian().try_inverse().ok_or_else(|| {
+            eyre!("Information matrix is singular; check for constant or collinear features")
+        })
+    }
+
+    ///
+    /// Standard errors of all_betas (intercept in the last slot); the square root
+    /// of the diagonal of the covariance.
+    ///
+    pub fn standard_errors(&self) -> Result<DVector<f64>> {
+        Ok(self.covariance()?.diagonal().map(f64::sqrt))
+    }
+
+    ///
+    /// Huber-White sandwich covariance `H^-1 (sum g_i g_i') H^-1` of all_betas,
+    /// where `g_i = w_i (y_i - p_i) x_i` is each record's score contribution
+    /// (scaled by `phi / (p (1 - p))` under the probit link).
+    /// Still valid when the model is misspecified, where `covariance` is not.
+    ///
+    pub fn robust_covariance(&self) -> Result<DMatrix<f64>> {
+        let bread = self.covariance()?;
+        let link = self.cfg.link;
+        let z = self.objective.log_odds(&self.all_betas);
+
+        let mut scores = self.objective.x.clone();
+        for (i, mut row) in scores.row_iter_mut().enumerate() {
+            let residual = self.objective.y[i] - link.inverse(z[i]);
+            row *= self.objective.weight(i) * residual * link.score_factor(z[i]);
+        }
+        let meat = scores.tr_mul(&scores);
+        Ok(&bread * meat * &bread)
+    }
+
+    /// Standard errors of all_betas from `robust_covariance`
+    pub fn robust_standard_errors(&self) -> Result<DVector<f64>> {
+        Ok(self.robust_covariance()?.diagonal().map(f64::sqrt))
+    }
+
+    /// Wald z-statistics `beta / se` for all_betas
+    pub fn z_statistics(&self) -> Result<DVector<f64>> {
+        Ok(self.all_betas.component_div(&self.standard_errors()?))
+    }
+
+    ///
+    /// Two-sided Wald p-values for all_betas (intercept in the last slot); small
+    /// values flag coefficients that are significantly different from zero.
+    ///
+    pub fn wald_pvalues(&self) -> Result<DVector<f64>> {
+        Ok(self.z_statistics()?.map(two_sided_pvalue))
+    }
+
+    ///
+    /// Two-sided Wald confidence intervals `beta ± z * se` for all_betas at the
+    /// requested level (e.g. 0.95).
+    ///
+    pub fn confidence_intervals(&self, level: f64) -> Result<Vec<(f64, f64)>> {
+        validate_level(level)?;
+        let z = normal_ppf(0.5 + level / 2.0);
+        let intervals = self
+            .all_betas
+            .iter()
+            .zip(self.standard_errors()?.iter())
+            .map(|(beta, se)| (beta - z * se, beta + z * se))
+            .collect();
+
+        Ok(intervals)
+    }
+
+    ///
+    /// `(probability, lower, upper)` for each record of `x` (the layout of
+    /// `decision_function_on`) at `level`. The interval is built on the linear
+    /// predictor, `eta ± z * sqrt(x' Cov x)`, which widens away from the data,
+    /// and both ends are mapped through the link, so it stays inside `(0, 1)`.
+    ///
+    pub fn predict_with_ci(&self, x: &DMatrix<f64>, level: f64) -> Result<Vec<(f64, f64, f64)>> {
+        validate_level(level)?;
+        let z = normal_ppf(0.5 + level / 2.0);
+        let log_odds = self.decision_function_on(x)?;
+        let cov = self.covariance()?;
+        let link = self.cfg.link;
+
+        Ok(x.row_iter()
+            .zip(log_odds.inner.iter())
+            .map(|(row, eta)| {
+                let se = (row * &cov).dot(&row).sqrt();
+                (
+                    link.inverse(*eta),
+                    link.inverse(eta - z * se),
+                    link.inverse(eta + z * se),
+                )
+            })
+            .collect())
+    }
+
+    ///
+    /// Variance inflation factor of a single coefficient at the fitted model; how
+    /// much its variance is inflated by correlation with the other predictors.
+    /// Computed from the covariance of the coefficients (intercept excluded)
+    /// rescaled to a correlation matrix.
+    ///
+    pub fn coefficient_vif(&self, feature: usize) -> Result<f64> {
+        let p = self.coefficients.len();
+        if feature >= p {
+            return Err(eyre!(
+                "Feature index {} out of range for {} coefficients (the intercept has no vif)",
+                feature,
+                p
+            ));
+        }
+
+        let cov = self.covariance()?;
+        let cov = cov.view((0, 0), (p, p));
+        let sd: DVector<f64> = cov.diagonal().map(f64::sqrt);
+        let corr = DMatrix::from_fn(p, p, |i, j| cov[(i, j)] / (sd[i] * sd[j]));
+
+        let corr_inv = corr
+            .try_inverse()
+            .ok_or_else(|| eyre!("Coefficient correlation matrix is singular"))?;
+
+        Ok(corr_inv[(feature, feature)])
+    }
+
+    ///
+    /// Standardized effect sizes: each coefficient times its predictor's std, so
+    /// features on different scales compare. Sorted by magnitude, largest first.
+    ///
+    pub fn feature_importance(&self) -> Vec<(String, f64)> {
+        let mut importance: Vec<(String, f64)> = self
+            .objective
+            .column_stats()
+            .iter()
+            .zip(self.coefficients.iter())
+            .enumerate()
+            .map(|(j, ((_, std), beta))| (self.objective.feature_name(j), beta * std))
+            .collect();
+        importance.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+        importance
+    }
+
+    ///
+    /// Average marginal effects: per predictor, the mean over the records of
+    /// `dp_i / dx_ij = p_i (1 - p_i) beta_j` (`phi(z_i) beta_j` for the probit),
+    /// the change in probability per unit of the predictor at the fitted
+    /// probabilities. Intercept excluded.
+    ///
+    pub fn average_marginal_effects(&self) -> DVector<f64> {
+        let slopes = self
+            .objective
+            .log_odds(&self.all_betas)
+            .map(|z| self.cfg.link.density(z));
+        // weighted records count that many times
+        let (total, weight) = (0..slopes.len()).fold((0.0, 0.0), |(total, weight), i| {
+            let w = self.objective.weight(i);
+            (total + w * slopes[i], weight + w)
+        });
+        &self.coefficients * (total / weight)
+    }
+
+    ///
+    /// Deviance of the intercept-only model; with `deviance`, the basis of the
+    /// pseudo-R² measures. With an offset the null model keeps it, so its
+    /// intercept is solved for under the fit's link rather than read off the
+    /// base rate.
+    ///
+    pub fn null_deviance(&self) -> f64 {
+        let y = &self.objective.y;
+        let p = self.objective.base_rate();
+        if let (Some(offset), true) = (&self.objective.offset, p > 0.0 && p < 1.0) {
+            let link = self.cfg.link;
+            let z = offset.add_scalar(null_intercept(self.objective, offset, link));
+            return 2.0 * link.neg_log_likelihood(&z, y, self.objective.weights.as_ref());
+        }
+        let ll: f64 = y
+            .iter()
+            .enumerate()
+            .map(|(i, yi)| {
+                // 0 * ln(0) is taken as 0 when y is all one class
+                let pos = if *yi > 0.0 { yi * p.ln() } else { 0.0 };
+                let neg = if *yi < 1.0 {
+                    (1.0 - yi) * (1.0 - p).ln()
+                } else {
+                    0.0
+                };
+                self.objective.weight(i) * (pos + neg)
+            })
+            .sum();
+        -2.0 * ll
+    }
+
+    /// Akaike information criterion `2k + 2 NLL`; k counts the intercept
+    pub fn aic(&self) -> f64 {
+        2.0 * self.all_betas.len() as f64 + self.deviance()
+    }
+
+    /// Bayesian information criterion `k ln(n) + 2 NLL`; penalizes k harder than aic once n > 7
+    pub fn bic(&self) -> f64 {
+        let n = self.objective.x.nrows() as f64;
+        self.all_betas.len() as f64 * n.ln() + self.deviance()
+    }
+}
+
+/// Newton stops once the intercept moves by less than this, or after the iterations
+const NULL_STEP_TOLERANCE: f64 = 1e-12;
+const NULL_MAX_ITERS: usize = 100;
+
+///
+/// The maximum likelihood intercept of the `link` model holding only the
+/// intercept and `offset`: Fisher scoring on the one beta (Newton for the
+/// logit), from the link of the base rate. The records must hold both classes.
+///
+fn null_intercept(objective: &Objective, offset: &DVector<f64>, link: Link) -> f64 {
+    let p = objective.base_rate();
+    let mut intercept = match link {
+        Link::Logit => (p / (1.0 - p)).ln(),
+        Link::Probit => normal_ppf(p),
+    };
+    for _ in 0..NULL_MAX_ITERS {
+        let (mut score, mut information) = (0.0, 0.0);
+        for (i, (yi, oi)) in objective.y.iter().zip(offset.iter()).enumerate() {
+            let z = intercept + oi;
+            let w = objective.weight(i);
+            score += w * (yi - link.inverse(z)) * link.score_factor(z);
+            information += w * link.fisher_weight(z);
+        }
+        let step = score / information;
+        intercept += step;
+        if step.abs() < NULL_STEP_TOLERANCE {
+            break;
+        }
+    }
+    intercept
+}
+
+impl<O> Findings<'_, O> {
+    /// `2 * NLL` at the fitted betas
+    pub fn deviance(&self) -> f64 {
+        2.0 * self.final_nll
+    }
+}
+
+///
+/// `X' W X` at the `log_odds` (`X * betas`, plus any offset) with the Fisher
+/// weights of `link`; for the logit also the Hessian of the negative
+/// log-likelihood
+///
+pub(crate) fn information_matrix(
+    x: &DMatrix<f64>,
+    log_odds: &DVector<f64>,
+    weights: Option<&DVector<f64>>,
+    link: Link,
+) -> DMatrix<f64> {
+    let mut w: DVector<f64> = log_odds.map(|z| link.fisher_weight(z));
+    if let Some(weights) = weights {
+        w.component_mul_assign(weights);
+    }
+
+    let mut wx = x.clone();
+    for (mut row, wi) in wx.row_iter_mut().zip(w.iter()) {
+        row *= *wi;
+    }
+    x.tr_mul(&wx)
+}
+
+pub(crate) fn validate_level(level: f64) -> Result<()> {
+    if level > 0.0 && level < 1.0 {
+        Ok(())
+    } else {
+        Err(eyre!("Confidence level must be in (0, 1), got {}", level))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::{CfgBuilder, CfgReport};
+    use crate::logit::{run, tests::toy};
+    use crate::models::sigmoid_f64;
+
+    #[test]
+    fn null_deviance_is_the_deviance_of_the_intercept_only_fit() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let null = Objective::new(objective.x.columns(1, 1).into_owned(), objective.y.clone());
+        let null_fit = null.fit(CfgBuilder::new().build()).unwrap();
+        assert!((findings.null_deviance() - null_fit.deviance()).abs() < 1e-8);
+        assert!(findings.deviance() < findings.null_deviance());
+    }
+
+    #[test]
+    fn null_deviance_keeps_the_offset() {
+        let objective = toy();
+        let offset = objective.x.column(0).map(|x| 0.3 * x);
+        let with_offset = toy().with_offset(offset.clone()).unwrap();
+        let findings = with_offset.fit(CfgBuilder::new().build()).unwrap();
+        let null = Objective::new(objective.x.columns(1, 1).into_owned(), objective.y.clone())
+            .with_offset(offset)
+            .unwrap();
+        let null_fit = null.fit(CfgBuilder::new().gtol(1e-10).build()).unwrap();
+        assert!((findings.null_deviance() - null_fit.deviance()).abs() < 1e-8);
+        // an offset that tracks y leaves the null model less unexplained
+        let plain = objective.fit(CfgBuilder::new().build()).unwrap();
+        assert!(plain.null_deviance() > findings.null_deviance());
+
+        // a probit fit's null model is a probit fit too
+        let probit = || CfgBuilder::new().link(Link::Probit).gtol(1e-10);
+        let findings = with_offset.fit(probit().build()).unwrap();
+        let null_fit = null.fit(probit().build()).unwrap();
+        assert!((findings.null_deviance() - null_fit.deviance()).abs() < 1e-8);
+    }
+
+    /// toy's predictor and a second one that largely tracks it
+    fn two_predictors() -> Objective {
+        let objective = toy();
+        let x = DMatrix::from_fn(objective.x.nrows(), 3, |i, j| match j {
+            0 => objective.x[(i, 0)],
+            1 => 0.5 * objective.x[(i, 0)] + ((i * 3) % 5) as f64 / 4.0,
+            _ => 1.0,
+        });
+        Objective::new(x, objective.y)
+    }
+
+    #[test]
+    fn coefficient_vif_flags_a_near_duplicate_but_not_an_independent_predictor() {
+        // x1 is toy's predictor up to a small wobble; x2 is orthogonal to both
+        let objective = toy();
+        let x = DMatrix::from_fn(objective.x.nrows(), 4, |i, j| match j {
+            0 => objective.x[(i, 0)],
+            1 => objective.x[(i, 0)] + [0.02, -0.02][i % 2],
+            2 => [1.0, -1.0, -1.0, 1.0][i % 4],
+            _ => 1.0,
+        });
+        let objective = Objective::new(x, objective.y);
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let vif: Vec<f64> = (0..3)
+            .map(|j| findings.coefficient_vif(j).unwrap())
+            .collect();
+        assert!(vif[0] > 100.0 && vif[1] > 100.0, "{:?}", vif);
+        assert!((vif[2] - 1.0).abs() < 0.1, "{:?}", vif);
+    }
+
+    #[test]
+    fn coefficient_vif_is_one_alone_and_inflated_by_a_correlated_predictor() {
+        let one = toy();
+        let alone = run(&one, CfgBuilder::new().build()).unwrap();
+        assert!((alone.coefficient_vif(0).unwrap() - 1.0).abs() < 1e-12);
+        assert!(alone.coefficient_vif(1).is_err());
+
+        let objective = two_predictors();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let cov = findings.covariance().unwrap();
+        let rho = cov[(0, 1)] / (cov[(0, 0)] * cov[(1, 1)]).sqrt();
+        let expected = 1.0 / (1.0 - rho * rho);
+        assert!(expected > 2.0);
+        for feature in 0..2 {
+            assert!((findings.coefficient_vif(feature).unwrap() - expected).abs() < 1e-9);
+        }
+    }
+
+    /// the first `n` of toy's targets against the intercept alone
+    fn intercept_only(n: usize) -> Objective {
+        let y = toy().y.rows(0, n).into_owned();
+        Objective::new(DMatrix::from_element(n, 1, 1.0), y)
+    }
+
+    #[test]
+    fn standard_errors_of_the_intercept_only_model_are_in_closed_form() {
+        // 6 of the first 15 records are positive
+        let objective = intercept_only(15);
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let se = findings.standard_errors().unwrap();
+        assert!((se[0] - 1.0 / (15.0f64 * 0.4 * 0.6).sqrt()).abs() < 1e-8);
+        // the square roots of the covariance's diagonal
+        let objective = two_predictors();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let cov = findings.covariance().unwrap();
+        let se = findings.standard_errors().unwrap();
+        for j in 0..3 {
+            assert_eq!(se[j], cov[(j, j)].sqrt());
+        }
+    }
+
+    #[test]
+    fn standard_errors_fail_on_a_singular_information_matrix() {
+        let objective = toy();
+        let x = DMatrix::from_fn(objective.x.nrows(), 3, |i, j| objective.x[(i, j.min(1))]);
+        let collinear = Objective::new(x, objective.y);
+        let findings = run(&collinear, CfgBuilder::new().build()).unwrap();
+        assert!(findings.standard_errors().is_err());
+    }
+
+    #[test]
+    fn wald_pvalues_are_large_for_an_irrelevant_random_predictor() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        // y depends on x0 only; x1 is drawn independently of it
+        let mut rng = StdRng::seed_from_u64(5);
+        let n = 200;
+        let x = DMatrix::from_fn(n, 3, |i, j| match j {
+            0 => i as f64 / n as f64 * 6.0 - 3.0,
+            1 => rng.gen_range(-1.0..1.0),
+            _ => 1.0,
+        });
+        let y = DVector::from_fn(n, |i, _| {
+            let p = sigmoid_f64(1.5 * x[(i, 0)]);
+            if rng.gen::<f64>() < p {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        let objective = Objective::new(x, y);
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let pvalues = findings.wald_pvalues().unwrap();
+        assert!(pvalues[0] < 1e-6, "{}", pvalues);
+        assert!(pvalues[1] > 0.2, "{}", pvalues);
+    }
+
+    #[test]
+    fn wald_pvalues_are_two_sided_normal_tails_of_beta_over_se() {
+        let objective = two_predictors();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let se = findings.standard_errors().unwrap();
+        let z = findings.z_statistics().unwrap();
+        let pvalues = findings.wald_pvalues().unwrap();
+        for j in 0..3 {
+            assert_eq!(z[j], findings.all_betas[j] / se[j]);
+            assert_eq!(pvalues[j], 2.0 * crate::stats::normal_cdf(-z[j].abs()));
+            assert!((0.0..=1.0).contains(&pvalues[j]));
+        }
+        // toy is balanced, so the intercept-only fit has a zero intercept and p = 1
+        let balanced = intercept_only(20);
+        let null = run(&balanced, CfgBuilder::new().build()).unwrap();
+        assert!((null.wald_pvalues().unwrap()[0] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn the_report_names_each_coefficient_with_its_z() {
+        let objective = two_predictors()
+            .with_feature_names(vec!["age".into(), "dose".into()])
+            .unwrap();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let report = findings.report().unwrap();
+        let z = findings.z_statistics().unwrap();
+        assert!(report.contains(&format!("\n  age: {}", findings.coefficients[0])));
+        assert!(report.contains(&format!("z: {:.3}", z[1])));
+    }
+
+    #[test]
+    fn confidence_intervals_are_beta_plus_minus_z_se() {
+        let objective = two_predictors();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let se = findings.standard_errors().unwrap();
+        let intervals = findings.confidence_intervals(0.95).unwrap();
+        let narrower = findings.confidence_intervals(0.5).unwrap();
+        for (j, (lower, upper)) in intervals.iter().enumerate() {
+            let beta = findings.all_betas[j];
+            assert!(((lower + upper) / 2.0 - beta).abs() < 1e-12);
+            assert!(((upper - lower) / 2.0 - 1.959_963_985 * se[j]).abs() < 1e-8);
+            assert!(narrower[j].1 - narrower[j].0 < upper - lower);
+        }
+        for level in [0.0, 1.0, -0.5, f64::NAN] {
+            assert!(findings.confidence_intervals(level).is_err());
+        }
+    }
+
+    #[test]
+    fn the_report_prints_the_intervals_at_the_requested_level() {
+        let objective = two_predictors();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let (lower, upper) = findings.confidence_intervals(0.9).unwrap()[0];
+        let cfg = CfgReport {
+            confidence_level: Some(0.9),
+            ..CfgReport::default()
+        };
+        let report = findings.report_with(&cfg).unwrap();
+        assert!(report.contains(&format!("90% CI: [{:.4}, {:.4}]", lower, upper)));
+        let cfg = CfgReport {
+            confidence_level: None,
+            ..CfgReport::default()
+        };
+        assert!(!findings.report_with(&cfg).unwrap().contains("CI:"));
+        let cfg = CfgReport {
+            confidence_level: Some(1.5),
+            ..CfgReport::default()
+        };
+        assert!(findings.report_with(&cfg).is_err());
+    }
+
+    #[test]
+    fn deviance_is_twice_the_final_nll() {
+        let objective = toy();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let nll = crate::logit::neg_log_likelihood(
+            &(&objective.x * &findings.all_betas),
+            &objective.y,
+            None,
+        );
+        assert_eq!(findings.final_nll, nll);
+        assert_eq!(findings.deviance(), 2.0 * nll);
+        // toy is balanced: the null model predicts 0.5 for every record
+        assert!((findings.null_deviance() - 40.0 * std::f64::consts::LN_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn aic_and_bic_penalize_the_deviance_by_the_parameter_count() {
+        let objective = two_predictors();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        assert_eq!(findings.aic(), 6.0 + findings.deviance());
+        assert_eq!(findings.bic(), 3.0 * 20f64.ln() + findings.deviance());
+        let report = findings.report().unwrap();
+        assert!(report.contains(&format!("AIC: {}\nBIC: {}", findings.aic(), findings.bic())));
+    }
+
+    #[test]
+    fn a_useless_predictor_raises_bic_more_than_aic() {
+        let objective = toy();
+        let x = DMatrix::from_fn(objective.x.nrows(), 3, |i, j| match j {
+            0 => objective.x[(i, 0)],
+            1 => ((i * 7) % 5) as f64,
+            _ => 1.0,
+        });
+        let padded = Objective::new(x, objective.y.clone());
+        let cfg = || CfgBuilder::new().build();
+        let base = run(&objective, cfg()).unwrap();
+        let wider = run(&padded, cfg()).unwrap();
+        let aic_rise = wider.aic() - base.aic();
+        let bic_rise = wider.bic() - base.bic();
+        // the deviance barely drops, so both criteria prefer the smaller model
+        assert!(aic_rise > 0.0, "aic rose by {}", aic_rise);
+        assert!(bic_rise > aic_rise, "bic rose by {}", bic_rise);
+    }
+
+    #[test]
+    fn feature_importance_is_the_std_scaled_coefficient_by_magnitude() {
+        let objective = two_predictors();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let importance = findings.feature_importance();
+        let stats = objective.column_stats();
+        assert_eq!(importance.len(), 2);
+        assert!(importance[0].1.abs() >= importance[1].1.abs());
+        for (name, value) in &importance {
+            let j = if name == "x0" { 0 } else { 1 };
+            assert_eq!(*value, findings.coefficients[j] * stats[j].1);
+        }
+    }
+
+    #[test]
+    fn feature_importance_ignores_a_predictors_units() {
+        let objective = two_predictors();
+        let mut scaled = two_predictors();
+        scaled.x.column_mut(0).scale_mut(1000.0);
+        let cfg = || CfgBuilder::new().gtol(1e-10).build();
+        let base = objective.fit(cfg()).unwrap();
+        let rescaled = scaled.fit(cfg()).unwrap();
+        let (a, b) = (base.feature_importance(), rescaled.feature_importance());
+        // the same ranking, by the same amounts
+        let names = |v: &[(String, f64)]| v.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&a), names(&b));
+        for ((_, x), (_, y)) in a.iter().zip(&b) {
+            assert!((x - y).abs() < 1e-4, "{} vs {}", x, y);
+        }
+        assert!((base.coefficients[0] - 1000.0 * rescaled.coefficients[0]).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hessian_is_the_sum_of_weighted_outer_products() {
+        let objective = two_predictors();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let hessian = findings.hessian();
+        assert_eq!(hessian.shape(), (3, 3));
+
+        let mut expected = DMatrix::zeros(3, 3);
+        for row in objective.x.row_iter() {
+            let p = sigmoid_f64(row.dot(&findings.all_betas.transpose()));
+            expected += p * (1.0 - p) * row.transpose() * row;
+        }
+        assert!((&hessian - &expected).amax() < 1e-10);
+        assert!((&hessian - hessian.transpose()).amax() < 1e-12);
+    }
+
+    #[test]
+    fn hessian_is_the_jacobian_of_the_gradient() {
+        use argmin::core::Gradient;
+
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let hessian = findings.hessian();
+        let eps = 1e-6;
+        for j in 0..2 {
+            let mut forward = findings.all_betas.clone();
+            forward[j] += eps;
+            let mut backward = findings.all_betas.clone();
+            backward[j] -= eps;
+            let column = ((&objective).gradient(&forward).unwrap()
+                - (&objective).gradient(&backward).unwrap())
+                / (2.0 * eps);
+            assert!((column - hessian.column(j)).amax() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn average_marginal_effects_match_a_finite_difference_of_the_probabilities() {
+        let objective = two_predictors();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let effects = findings.average_marginal_effects();
+        assert_eq!(effects.len(), 2);
+
+        let h = 1e-6;
+        for j in 0..2 {
+            let mean_probability = |shift: f64| {
+                let mut x = objective.x.clone();
+                x.column_mut(j).add_scalar_mut(shift);
+                findings.predict_proba_on(&x).unwrap().mean()
+            };
+            let numeric = (mean_probability(h) - mean_probability(-h)) / (2.0 * h);
+            assert!(
+                (effects[j] - numeric).abs() < 1e-7,
+                "{} vs {}",
+                effects[j],
+                numeric
+            );
+        }
+    }
+
+    #[test]
+    fn the_report_lists_the_marginal_effects_on_request() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let effect = findings.average_marginal_effects()[0];
+        let cfg = CfgReport {
+            marginal_effects: true,
+            ..CfgReport::default()
+        };
+        let report = findings.report_with(&cfg).unwrap();
+        assert!(report.contains(&format!("average marginal effects:\n  x0: {}", effect)));
+        assert!(!findings.report().unwrap().contains("marginal"));
+    }
+
+    #[test]
+    fn robust_standard_errors_of_the_intercept_only_model_match_the_model_ones() {
+        // at p = the base rate, sum (y - p)^2 = n p (1 - p): the meat is the bread's inverse
+        let objective = intercept_only(15);
+        let findings = objective
+            .fit(CfgBuilder::new().gtol(1e-10).build())
+            .unwrap();
+        let robust = findings.robust_standard_errors().unwrap();
+        assert!((robust[0] - 1.0 / (15.0f64 * 0.4 * 0.6).sqrt()).abs() < 1e-8);
+    }
+
+    #[test]
+    fn robust_covariance_is_the_sandwich_of_the_score_contributions() {
+        let objective = two_predictors();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let robust = findings.robust_covariance().unwrap();
+
+        let bread = findings.covariance().unwrap();
+        let mut meat = DMatrix::zeros(3, 3);
+        for (row, yi) in objective.x.row_iter().zip(objective.y.iter()) {
+            let p = sigmoid_f64(row.dot(&findings.all_betas.transpose()));
+            let g = row.transpose() * (yi - p);
+            meat += &g * g.transpose();
+        }
+        assert!((&robust - &bread * meat * &bread).amax() < 1e-10);
+        assert!((&robust - robust.transpose()).amax() < 1e-12);
+        let se = findings.robust_standard_errors().unwrap();
+        assert_eq!(se, robust.diagonal().map(f64::sqrt));
+    }
+
+    #[test]
+    fn predict_with_ci_of_the_intercept_only_model_is_the_log_odds_interval() {
+        // the intercept's se is 1 / sqrt(n p q), mapped back through the sigmoid
+        let objective = intercept_only(15);
+        let findings = objective
+            .fit(CfgBuilder::new().gtol(1e-10).build())
+            .unwrap();
+        let x = DMatrix::from_element(2, 1, 1.0);
+        let intervals = findings.predict_with_ci(&x, 0.95).unwrap();
+        assert_eq!(intervals.len(), 2);
+        let (p, lo, hi) = intervals[0];
+        let eta = (0.4f64 / 0.6).ln();
+        let half = normal_ppf(0.975) / (15.0f64 * 0.4 * 0.6).sqrt();
+        assert!((p - 0.4).abs() < 1e-8);
+        assert!((lo - sigmoid_f64(eta - half)).abs() < 1e-8);
+        assert!((hi - sigmoid_f64(eta + half)).abs() < 1e-8);
+    }
+
+    #[test]
+    fn predict_with_ci_widens_away_from_the_training_mean() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let mean = objective.x.column(0).mean();
+        let x = DMatrix::from_row_slice(2, 2, &[mean, 1.0, mean + 6.0, 1.0]);
+        let intervals = findings.predict_with_ci(&x, 0.95).unwrap();
+        let logit = |p: f64| (p / (1.0 - p)).ln();
+        let width = |(_, lo, hi): (f64, f64, f64)| logit(hi) - logit(lo);
+        assert!(width(intervals[1]) > 2.0 * width(intervals[0]));
+        // the far row's probability band is still inside (0, 1)
+        let (_, lo, hi) = intervals[1];
+        assert!(lo > 0.0 && hi < 1.0);
+    }
+
+    #[test]
+    fn predict_with_ci_widens_with_the_level_and_stays_in_the_unit_interval() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let narrow = findings.predict_with_ci(&objective.x, 0.5).unwrap();
+        let wide = findings.predict_with_ci(&objective.x, 0.99).unwrap();
+        let probabilities = findings.predict_proba();
+        for ((n, w), p) in narrow.iter().zip(&wide).zip(probabilities.iter()) {
+            assert_eq!(n.0, *p);
+            assert!(w.1 <= n.1 && n.1 <= n.0 && n.0 <= n.2 && n.2 <= w.2);
+            assert!(w.1 >= 0.0 && w.2 <= 1.0);
+        }
+        assert!(findings.predict_with_ci(&objective.x, 1.0).is_err());
+        assert!(findings
+            .predict_with_ci(&DMatrix::from_element(1, 3, 1.0), 0.9)
+            .is_err());
+    }
+}
@@ -1,12 +1,48 @@
 mod auc_score;
+mod calibration;
 mod configurations;
+mod inference;
+mod link;
 pub mod logit;
 mod matrix_csv;
+mod metrics;
+mod model;
 mod models;
+pub mod multinomial;
+#[cfg(feature = "ndarray")]
+mod ndarray_input;
+mod observers;
+#[cfg(feature = "parquet")]
+mod parquet_reader;
+mod pipeline;
+#[cfg(feature = "polars")]
+mod polars_input;
+mod preprocessing;
+mod regularization;
+mod repro;
+#[cfg(feature = "sparse")]
+mod sparse;
+mod stats;
+mod stopping;
+mod validation;
 
 pub mod prelude {
+    pub use argmin::core::TerminationReason;
 
+    pub use crate::auc_score::*;
+    pub use crate::calibration::*;
     pub use crate::configurations::*;
+    pub use crate::link::Link;
     pub use crate::logit;
+    pub use crate::metrics::*;
+    pub use crate::model::*;
     pub use crate::models::*;
+    pub use crate::multinomial;
+    pub use crate::multinomial::{Multinomial, MultinomialFindings};
+    pub use crate::pipeline::*;
+    pub use crate::regularization::regularization_path;
+    pub use crate::repro::*;
+    #[cfg(feature = "sparse")]
+    pub use crate::sparse::*;
+    pub use crate::validation::*;
 }
@@ -0,0 +1,235 @@
+use argmin::core::{CostFunction, Error, Gradient};
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::PI;
+
+use crate::inference::information_matrix;
+use crate::logit::{self, check_param_len, Design};
+use crate::models::{sigmoid_f64, Objective};
+use crate::stats::normal_cdf;
+
+///
+/// How the linear predictor `z = X * w` maps to the probability of the positive
+/// class; chosen with `CfgBuilder::link`. The fit, the predictions and the
+/// inference on `Findings` all follow it.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Link {
+    /// `p = 1 / (1 + e^-z)`; the coefficients are log odds ratios
+    #[default]
+    Logit,
+    /// `p = Phi(z)`, the standard normal CDF; dense objectives only
+    Probit,
+}
+
+impl Link {
+    /// The probability at `z`
+    pub fn inverse(&self, z: f64) -> f64 {
+        match self {
+            Link::Logit => sigmoid_f64(z),
+            Link::Probit => normal_cdf(z).clamp(0.0, 1.0),
+        }
+    }
+    pub fn inverse_vec(&self, z: &DVector<f64>) -> DVector<f64> {
+        z.map(|z| self.inverse(z))
+    }
+    /// `dp / dz` at `z`
+    pub(crate) fn density(&self, z: f64) -> f64 {
+        match self {
+            Link::Logit => {
+                let p = sigmoid_f64(z);
+                p * (1.0 - p)
+            }
+            Link::Probit => normal_pdf(z),
+        }
+    }
+    /// `p (1 - p)` at `z`; for the probit each factor is its own CDF, so the
+    /// product stays accurate in both tails
+    fn variance(&self, z: f64) -> f64 {
+        match self {
+            Link::Logit => self.density(z),
+            Link::Probit => (normal_cdf(z) * normal_cdf(-z)).max(f64::MIN_POSITIVE),
+        }
+    }
+    /// Turns the residual `y - p` into the record's score `d loglik / dz`; 1 for the logit
+    pub(crate) fn score_factor(&self, z: f64) -> f64 {
+        match self {
+            Link::Logit => 1.0,
+            Link::Probit => self.density(z) / self.variance(z),
+        }
+    }
+    /// The negative log-likelihood of `y` at the linear predictor `z`
+    pub(crate) fn neg_log_likelihood(
+        &self,
+        z: &DVector<f64>,
+        y: &DVector<f64>,
+        weights: Option<&DVector<f64>>,
+    ) -> f64 {
+        match self {
+            Link::Logit => logit::neg_log_likelihood(z, y, weights),
+            Link::Probit => z
+                .iter()
+                .zip(y)
+                .enumerate()
+                .map(|(i, (zi, yi))| {
+                    // ln(1 - Phi(z)) is ln Phi(-z), accurate where 1 - Phi(z) is not
+                    let signed = if *yi == 1.0 { *zi } else { -zi };
+                    let loss = -normal_cdf(signed).max(f64::MIN_POSITIVE).ln();
+                    weights.map_or(loss, |w| w[i] * loss)
+                })
+                .sum(),
+        }
+    }
+    /// The record's weight in the Fisher information, `p (1 - p)` for the logit
+    pub(crate) fn fisher_weight(&self, z: f64) -> f64 {
+        match self {
+            Link::Logit => self.density(z),
+            Link::Probit => self.density(z).powi(2) / self.variance(z),
+        }
+    }
+}
+
+fn normal_pdf(z: f64) -> f64 {
+    (-z * z / 2.0).exp() / (2.0 * PI).sqrt()
+}
+
+///
+/// A dense `Objective` under the probit link; `logit::run` fits it in place of
+/// the objective when the cfg asks for `Link::Probit`.
+///
+#[derive(Debug)]
+pub(crate) struct Probit<'a>(&'a Objective);
+
+impl<'a> Probit<'a> {
+    pub(crate) fn of<D: Design + ?Sized>(design: &'a D) -> Result<Self> {
+        design
+            .dense()
+            .map(Probit)
+            .ok_or_else(|| eyre!("The probit link needs a dense `Objective`"))
+    }
+}
+
+impl Design for Probit<'_> {
+    fn feature_count(&self) -> usize {
+        self.0.feature_count()
+    }
+    fn record_count(&self) -> usize {
+        self.0.nrows()
+    }
+    /// the expected information; Newton on it is Fisher scoring
+    fn hessian(&self, param: &DVector<f64>) -> Result<DMatrix<f64>> {
+        Ok(information_matrix(
+            &self.0.x,
+            &self.0.log_odds(param),
+            self.0.weights.as_ref(),
+            Link::Probit,
+        ))
+    }
+    fn linear_predictor(&self, param: &DVector<f64>) -> Option<DVector<f64>> {
+        Some(self.0.log_odds(param))
+    }
+    fn batch_gradient(&self, param: &DVector<f64>, rows: &[usize]) -> Result<DVector<f64>> {
+        let x = self.0.x.select_rows(rows);
+        let mut z = &x * param;
+        if let Some(offset) = &self.0.offset {
+            z += offset.select_rows(rows);
+        }
+        let residual = DVector::from_fn(rows.len(), |k, _| {
+            self.0.weight(rows[k]) * score(z[k], self.0.y[rows[k]])
+        });
+        Ok(-x.tr_mul(&residual))
+    }
+}
+
+/// `d loglik / dz` of a record
+fn score(z: f64, y: f64) -> f64 {
+    (y - Link::Probit.inverse(z)) * Link::Probit.score_factor(z)
+}
+
+impl CostFunction for &Probit<'_> {
+    type Param = DVector<f64>;
+    type Output = f64;
+
+    fn cost(&self, param: &Self::Param) -> Result<Self::Output, Error> {
+        check_param_len(self.0.feature_count(), param)?;
+        Ok(Link::Probit.neg_log_likelihood(
+            &self.0.log_odds(param),
+            &self.0.y,
+            self.0.weights.as_ref(),
+        ))
+    }
+}
+
+impl Gradient for &Probit<'_> {
+    type Param = DVector<f64>;
+    type Gradient = DVector<f64>;
+
+    fn gradient(&self, param: &Self::Param) -> Result<Self::Gradient, Error> {
+        check_param_len(self.0.feature_count(), param)?;
+        let z = self.0.log_odds(param);
+        let residual =
+            DVector::from_fn(z.len(), |i, _| self.0.weight(i) * score(z[i], self.0.y[i]));
+        Ok(-self.0.x.tr_mul(&residual))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::{run, tests::toy};
+
+    #[test]
+    fn inverse_links_are_probabilities_centred_at_zero() {
+        for link in [Link::Logit, Link::Probit] {
+            // the normal cdf is an approximation good to ~1e-7
+            assert!((link.inverse(0.0) - 0.5).abs() < 1e-7);
+            assert!(link.inverse(-40.0) >= 0.0 && link.inverse(40.0) <= 1.0);
+            assert!((link.inverse(1.0) + link.inverse(-1.0) - 1.0).abs() < 1e-7);
+        }
+        assert!((Link::Probit.inverse(1.0) - 0.841_344_746_068_543).abs() < 1e-7);
+    }
+
+    #[test]
+    fn probit_fits_close_to_but_apart_from_the_logit() {
+        let objective = toy();
+        let logit = run(&objective, CfgBuilder::new().build()).unwrap();
+        let probit = run(&objective, CfgBuilder::new().link(Link::Probit).build()).unwrap();
+        // the logistic's sd is ~1.6-1.8 times the normal's, and the slopes scale by it
+        let ratio = logit.coefficients[0] / probit.coefficients[0];
+        assert!((1.5..1.9).contains(&ratio), "ratio {}", ratio);
+        assert!(probit.gradient_norm < 1e-4);
+        assert_ne!(probit.final_nll, logit.final_nll);
+        assert!((probit.final_nll - logit.final_nll).abs() < 0.5);
+        let probabilities = probit.predict_proba();
+        assert!(probabilities.inner.iter().all(|p| (0.0..=1.0).contains(p)));
+        for (p, z) in probabilities
+            .inner
+            .iter()
+            .zip(probit.decision_function().inner.iter())
+        {
+            assert_eq!(*p, normal_cdf(*z).clamp(0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn probit_batch_gradients_over_a_partition_sum_to_the_full_gradient() {
+        let offset = DVector::from_fn(20, |i, _| (i % 3) as f64 * 0.1);
+        let weights = DVector::from_fn(20, |i, _| 1.0 + (i % 2) as f64);
+        let mut objective = toy().with_offset(offset).unwrap();
+        objective.weights = Some(weights);
+        let probit = Probit::of(&objective).unwrap();
+        let param = DVector::from_vec(vec![0.7, -0.2]);
+        let rows: Vec<usize> = (0..20).collect();
+        let (evens, odds): (Vec<usize>, Vec<usize>) = rows.iter().partition(|i| *i % 2 == 0);
+        let summed = probit.batch_gradient(&param, &evens).unwrap()
+            + probit.batch_gradient(&param, &odds).unwrap();
+        let full = (&probit).gradient(&param).unwrap();
+        assert!((summed - &full).norm() < 1e-12, "full {}", full);
+        assert!(full.norm() > 0.1);
+    }
+}
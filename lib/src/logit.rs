@@ -1,22 +1,33 @@
 use argmin::core::observers::slog_logger::SlogLogger;
 use argmin::core::observers::ObserverMode;
-use argmin::core::{CostFunction, Error, Executor, Gradient};
+use argmin::core::{
+    CostFunction, Error, Executor, Gradient, State, TerminationReason, TerminationStatus,
+};
 use argmin::solver::linesearch::condition::ArmijoCondition;
 use argmin::solver::linesearch::BacktrackingLineSearch;
 use argmin::solver::quasinewton::LBFGS;
 
 use color_eyre::eyre::{eyre, Result};
-use nalgebra::base::DVector;
+use nalgebra::base::{DMatrix, DVector};
+use rand::seq::SliceRandom;
 use tracing::{event, Level};
 
-use std::iter::zip;
+use std::collections::HashMap;
+use std::time::Instant;
 
 use crate::configurations::*;
-use crate::models::{sigmoid, Findings, Objective};
+use crate::inference::information_matrix;
+use crate::link::{Link, Probit};
+use crate::models::{sigmoid_vec, Findings, Objective, Prediction};
+use crate::observers::CostHistory;
+#[cfg(feature = "progress")]
+use crate::observers::Progress;
+use crate::regularization::{ProfiledIntercept, Ridge};
+use crate::stopping::{expired, Deadline, Monitor, EARLY_STOPPED, TIMED_OUT};
 
 // ✅ Replicates the original
 /// use trait to specify how use data to compute objective
-impl<'a> CostFunction for &'a Objective {
+impl CostFunction for &Objective {
     type Param = DVector<f64>;
     type Output = f64;
 
@@ -32,78 +43,516 @@ impl<'a> CostFunction for &'a Objective {
         event!(Level::DEBUG, "🦀 -----------------------------------");
         */
 
-        assert!(
-            self.x.row(0).len() == ws.len(),
-            "🦀 x feature count not matching guess param len"
-        );
-        assert!(
-            self.feature_count() == ws.len(),
-            "🦀 feature count not matching guess size"
-        );
+        check_param_len(self.feature_count(), ws)?;
 
         // the guess includes a slot for the intercept/bias
-        // create a view that clips the first value
-        let cost: f64 = (&self.x * ws)
-            .iter_mut()
-            .map(|&mut raw_y_hat| sigmoid(raw_y_hat))
-            .zip(&self.y)
-            .map(|(y_hat, yi)| yi * y_hat.ln() + (1.0 - yi) * (1.0 - y_hat).ln())
-            .sum();
+        Ok(neg_log_likelihood(
+            &self.log_odds(ws),
+            &self.y,
+            self.weights.as_ref(),
+        ))
+    }
+}
 
-        Ok(-cost)
+/// The param must have a slot for every column of x, including the intercept
+pub(crate) fn check_param_len(feature_count: usize, param: &DVector<f64>) -> Result<(), Error> {
+    if feature_count == param.len() {
+        Ok(())
+    } else {
+        Err(Error::msg(format!(
+            "🦀 feature count {} not matching param len {}",
+            feature_count,
+            param.len()
+        )))
     }
 }
 
+///
+/// The logit loss given the linear predictor `z = X * w`. Per record
+/// `-[y ln p + (1 - y) ln(1 - p)]` with `p = sigmoid(z)` equals
+/// `softplus(z) - y z`, which never evaluates `ln(0)` however confident `z` is.
+/// With `weights` each record's loss counts that many times.
+///
+pub(crate) fn neg_log_likelihood(
+    z: &DVector<f64>,
+    y: &DVector<f64>,
+    weights: Option<&DVector<f64>>,
+) -> f64 {
+    z.iter()
+        .zip(y)
+        .enumerate()
+        .map(|(i, (zi, yi))| {
+            let loss = softplus(*zi) - yi * zi;
+            weights.map_or(loss, |w| w[i] * loss)
+        })
+        .sum()
+}
+
+/// `ln(1 + e^z)` without overflow for large `z` or lost precision for small
+fn softplus(z: f64) -> f64 {
+    z.max(0.0) + (-z.abs()).exp().ln_1p()
+}
+
 // ✅ Replicates the original
 /// First or second derivative to help find max and min
-impl<'a> Gradient for &'a Objective {
+impl Gradient for &Objective {
     type Param = DVector<f64>;
     type Gradient = DVector<f64>;
 
     #[tracing::instrument]
     fn gradient(&self, param: &Self::Param) -> Result<Self::Gradient, Error> {
         let ws = param;
-        let n = self.feature_count();
+        check_param_len(self.feature_count(), ws)?;
+
+        // X' W (sigmoid(X w) - y)
+        let mut residual: DVector<f64> = sigmoid_vec(&self.log_odds(ws)) - &self.y;
+        if let Some(weights) = &self.weights {
+            residual.component_mul_assign(weights);
+        }
+        Ok(self.x.tr_mul(&residual))
+    }
+}
 
-        let dyi_x_n: DVector<f64> = (&self.x * ws).map(|raw_y_hat| sigmoid(raw_y_hat)) - &self.y;
+///
+/// Max absolute difference between the analytic gradient at `param` and its
+/// central finite difference `(cost(w + eps) - cost(w - eps)) / 2 eps`. Small
+/// (relative to the gradient) when the two agree; for debugging objectives.
+///
+pub fn check_gradient(obj: &Objective, param: &DVector<f64>, eps: f64) -> Result<f64> {
+    let cost = |w: &DVector<f64>| obj.cost(w).map_err(|e| eyre!("Cost failed: {}", e));
+    let analytic = obj
+        .gradient(param)
+        .map_err(|e| eyre!("Gradient failed: {}", e))?;
 
-        // zip fold
-        // Note: For now unable to accomplish this without using Vec instead of DVector
-        let result: Vec<f64> =
-            zip(self.x.row_iter(), &dyi_x_n).fold(vec![0.0; n], |acc, (xs, dyi)| {
-                // event!(Level::DEBUG, "\n🦀 acc: {:?}", &acc);
-                // zip map
-                zip(acc, xs.iter())
-                    .map(|(mut acc_j, x_j)| {
-                        acc_j += x_j * dyi;
-                        acc_j
-                    })
-                    .collect()
-            });
+    let mut max_diff = 0f64;
+    for j in 0..param.len() {
+        let mut forward = param.clone();
+        forward[j] += eps;
+        let mut backward = param.clone();
+        backward[j] -= eps;
+        let numeric = (cost(&forward)? - cost(&backward)?) / (2.0 * eps);
+        max_diff = max_diff.max((numeric - analytic[j]).abs());
+    }
+    Ok(max_diff)
+}
 
-        Ok(result.into())
+///
+/// A design matrix the solver can fit: the dense `Objective` and, with the
+/// `sparse` feature, `SparseObjective`. The param includes the intercept slot.
+///
+pub trait Design {
+    fn feature_count(&self) -> usize;
+    fn record_count(&self) -> usize;
+    /// length of the flattened param the solver searches over
+    fn param_len(&self) -> usize {
+        self.feature_count()
+    }
+    /// Hessian of the cost at `param`; required by `SolverKind::Newton`
+    fn hessian(&self, _param: &DVector<f64>) -> Result<DMatrix<f64>> {
+        Err(eyre!(
+            "The Newton solver is not available for this objective"
+        ))
+    }
+    /// `X * param`, when the objective has a single linear predictor
+    fn linear_predictor(&self, _param: &DVector<f64>) -> Option<DVector<f64>> {
+        None
+    }
+    /// Gradient of the cost summed over `rows`; required by `SolverKind::Sgd`
+    fn batch_gradient(&self, _param: &DVector<f64>, _rows: &[usize]) -> Result<DVector<f64>> {
+        Err(eyre!("The sgd solver is not available for this objective"))
+    }
+    /// Condition number of the design; `None` skips the collinearity check
+    fn condition_number(&self) -> Option<f64> {
+        None
+    }
+    /// The dense objective behind the design, which the probit link requires
+    fn dense(&self) -> Option<&Objective> {
+        None
+    }
+}
+
+impl Design for Objective {
+    fn feature_count(&self) -> usize {
+        Objective::feature_count(self)
+    }
+    fn record_count(&self) -> usize {
+        self.x.nrows()
+    }
+    fn hessian(&self, param: &DVector<f64>) -> Result<DMatrix<f64>> {
+        Ok(information_matrix(
+            &self.x,
+            &self.log_odds(param),
+            self.weights.as_ref(),
+            Link::Logit,
+        ))
+    }
+    fn linear_predictor(&self, param: &DVector<f64>) -> Option<DVector<f64>> {
+        Some(self.log_odds(param))
+    }
+    fn batch_gradient(&self, param: &DVector<f64>, rows: &[usize]) -> Result<DVector<f64>> {
+        let x = self.x.select_rows(rows);
+        let y = self.y.select_rows(rows);
+        let mut z = &x * param;
+        if let Some(offset) = &self.offset {
+            z += offset.select_rows(rows);
+        }
+        let mut residual = sigmoid_vec(&z) - y;
+        if let Some(weights) = &self.weights {
+            residual.component_mul_assign(&weights.select_rows(rows));
+        }
+        Ok(x.tr_mul(&residual))
+    }
+    fn condition_number(&self) -> Option<f64> {
+        Some(Objective::condition_number(self))
+    }
+    fn dense(&self) -> Option<&Objective> {
+        Some(self)
     }
 }
 
 // #[tracing::instrument]
-pub fn run<'a>(
-    objective: &'a Objective,
-    Cfg {
-        max_iters, logging, ..
-    }: Cfg,
-) -> Result<Findings> {
-    // Enter the span, returning a guard object.
+pub fn run<'a, D>(objective: &'a D, cfg: Cfg) -> Result<Findings<'a, D>>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    run_monitored(objective, cfg, None)
+}
+
+///
+/// `run` that also hands back the solver's bookkeeping, for inspecting how the
+/// fit went without forking the crate
+///
+pub fn run_verbose<'a, D>(objective: &'a D, cfg: Cfg) -> Result<(Findings<'a, D>, SolverStats)>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    fit_findings(objective, cfg, None)
+}
 
+/// `run` with a `Monitor` that can stop the fit early
+pub(crate) fn run_monitored<'a, D>(
+    objective: &'a D,
+    cfg: Cfg,
+    monitor: Option<Monitor>,
+) -> Result<Findings<'a, D>>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    fit_findings(objective, cfg, monitor).map(|(findings, _)| findings)
+}
+
+fn fit_findings<'a, D>(
+    objective: &'a D,
+    cfg: Cfg,
+    monitor: Option<Monitor>,
+) -> Result<(Findings<'a, D>, SolverStats)>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
     event!(
         Level::INFO,
         "🟢 Running the optimization with feature count: {}",
         objective.feature_count()
     );
 
+    cfg.check_penalties()?;
+
     let p = objective.feature_count();
+    let fit = match cfg.link {
+        Link::Logit => fit_penalized(objective, &cfg, monitor)?,
+        Link::Probit => fit_penalized::<Probit>(&Probit::of(objective)?, &cfg, monitor)?,
+    };
+    let w = &fit.param;
+    let gradient_norm = gradient_norm(objective, &cfg, w)?;
+
+    let separation_warning = separation_suspected(&w.rows(0, p - 1).into_owned(), &fit);
+    if separation_warning {
+        event!(
+            Level::WARN,
+            "🟡 Possible perfect separation: a coefficient exceeds {} in magnitude while the \
+             cost is still falling toward zero. The betas are not reliable; consider \
+             regularization or dropping the separating feature.",
+            SEPARATION_BETA_LIMIT
+        );
+    }
+
+    let condition_number = objective.condition_number();
+    let collinearity_warning = condition_number.is_some_and(|c| c > CONDITION_NUMBER_LIMIT);
+    if collinearity_warning {
+        event!(
+            Level::WARN,
+            "🟡 The design is ill-conditioned (condition number {:e}): a feature is (nearly) \
+             a linear combination of others. Consider dropping one of the collinear features.",
+            condition_number.unwrap_or_default()
+        );
+    }
+
+    let default_prediction = cfg.cfg_predict.as_ref().and_then(|cfg_predict| {
+        let probabilities = cfg.link.inverse_vec(&objective.linear_predictor(w)?);
+        Some(Prediction::from_cfg(probabilities, cfg_predict))
+    });
+
+    let stats = SolverStats {
+        cost_count: fit.count("cost_count"),
+        gradient_count: fit.count("gradient_count"),
+        hessian_count: fit.count("hessian_count"),
+        final_cost: fit.cost,
+        func_counts: fit.func_counts.clone(),
+    };
+
+    let findings = Findings {
+        all_betas: w.rows(0, p).into_owned(),
+        coefficients: w.rows(0, p - 1).into_owned(),
+        intercept: w[p - 1],
+        objective,
+        cfg,
+        final_nll: fit.cost,
+        gradient_norm,
+        timed_out: fit.timed_out(),
+        cost_history: fit.cost_history,
+        separation_warning,
+        collinearity_warning,
+        default_prediction,
+        iterations: fit.iterations,
+        termination: fit.termination,
+    };
+    Ok((findings, stats))
+}
+
+/// The norm of the gradient of the cost `cfg` minimizes (penalty included) at `param`
+pub(crate) fn gradient_norm<'a, D>(objective: &'a D, cfg: &Cfg, param: &DVector<f64>) -> Result<f64>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    match cfg.link {
+        Link::Logit => penalized_gradient_norm(objective, cfg, param),
+        Link::Probit => penalized_gradient_norm::<Probit>(&Probit::of(objective)?, cfg, param),
+    }
+}
+
+fn penalized_gradient_norm<'a, D>(objective: &'a D, cfg: &Cfg, param: &DVector<f64>) -> Result<f64>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    let gradient = if cfg.l2 == 0.0 {
+        objective.gradient(param)
+    } else {
+        (&Ridge::new(objective, cfg.l2)?).gradient(param)
+    };
+    let gradient = gradient.map_err(|e| eyre!("Result failed: {}", e))?;
+    if cfg.l1 == 0.0 {
+        return Ok(gradient.norm());
+    }
+    // the smallest subgradient of `l1 * |w|_1` over the slopes; the intercept is unpenalized
+    let last = param.len() - 1;
+    let subgradient = DVector::from_fn(param.len(), |j, _| {
+        let (g, w) = (gradient[j], param[j]);
+        match (j == last, w == 0.0) {
+            (true, _) => g,
+            (false, true) => g.signum() * (g.abs() - cfg.l1).max(0.0),
+            (false, false) => g + cfg.l1 * w.signum(),
+        }
+    });
+    Ok(subgradient.norm())
+}
+
+/// The fit of `objective` plus the `cfg.l2` and `cfg.l1` penalties; the cost
+/// reported is the likelihood part, so deviance and the criteria stay comparable
+fn fit_penalized<'a, D>(objective: &'a D, cfg: &Cfg, monitor: Option<Monitor>) -> Result<Fit>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    if cfg.l2 == 0.0 && cfg.l1 == 0.0 {
+        return solve(objective, cfg, monitor);
+    }
+    let ridge = (cfg.l2 != 0.0)
+        .then(|| Ridge::new(objective, cfg.l2))
+        .transpose()?;
+    let mut fit = match (&ridge, cfg.l1 > 0.0) {
+        (None, false) => solve(objective, cfg, monitor)?,
+        (Some(ridge), false) => solve::<Ridge>(ridge, cfg, monitor)?,
+        (None, true) => fit_lasso(&ProfiledIntercept::new(objective), cfg, monitor)?,
+        (Some(ridge), true) => fit_lasso(&ProfiledIntercept::new::<Ridge>(ridge), cfg, monitor)?,
+    };
+    fit.cost = objective
+        .cost(&fit.param)
+        .map_err(|e| eyre!("Result failed: {}", e))?;
+    Ok(fit)
+}
+
+///
+/// The OWL-QN fit over the slopes of `profiled`, returned with its intercept;
+/// the warm start and the monitor see the full betas
+///
+fn fit_lasso(profiled: &ProfiledIntercept, cfg: &Cfg, monitor: Option<Monitor>) -> Result<Fit> {
+    let mut slopes_cfg = cfg.clone();
+    if let Some(betas) = &cfg.warm_start {
+        if betas.len() != profiled.param_len() + 1 {
+            return Err(eyre!(
+                "The warm start has {} betas; the objective needs {}",
+                betas.len(),
+                profiled.param_len() + 1
+            ));
+        }
+        profiled.start_from(betas);
+        slopes_cfg.warm_start = Some(betas.rows(0, betas.len() - 1).into_owned());
+    }
+    let mut fit = match monitor {
+        None => minimize(profiled, &slopes_cfg, None)?,
+        Some(monitor) => {
+            let mut full =
+                |slopes: &DVector<f64>| profiled.full(slopes).is_ok_and(|betas| monitor(&betas));
+            minimize(profiled, &slopes_cfg, Some(&mut full))?
+        }
+    };
+    fit.param = profiled
+        .full(&fit.param)
+        .map_err(|e| eyre!("Result failed: {}", e))?;
+    Ok(fit)
+}
+
+/// The fit by `cfg.solver`
+fn solve<'a, D>(objective: &'a D, cfg: &Cfg, monitor: Option<Monitor>) -> Result<Fit>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    match cfg.solver {
+        SolverKind::Lbfgs | SolverKind::OwlQn => minimize(objective, cfg, monitor),
+        SolverKind::Newton => newton(objective, cfg, monitor),
+        SolverKind::Sgd {
+            batch_size,
+            learning_rate,
+            epochs,
+            momentum,
+        } => sgd(
+            objective,
+            cfg,
+            monitor,
+            batch_size,
+            learning_rate,
+            epochs,
+            momentum,
+        ),
+    }
+}
+
+/// Past this the betas swing with tiny changes to the data
+const CONDITION_NUMBER_LIMIT: f64 = 1e6;
+
+/// Log-odds per unit of a feature that real data rarely produces
+const SEPARATION_BETA_LIMIT: f64 = 10.0;
+
+///
+/// When a feature separates the classes the likelihood has no maximum: the
+/// betas grow without bound while the cost keeps shrinking toward zero.
+///
+fn separation_suspected(coefficients: &DVector<f64>, fit: &Fit) -> bool {
+    let diverging = coefficients.iter().any(|b| b.abs() > SEPARATION_BETA_LIMIT);
+    let still_falling = match fit.cost_history.as_slice() {
+        [.., previous, last] => last < previous,
+        _ => false,
+    };
+    diverging && (still_falling || fit.cost < 1e-6)
+}
+
+/// What a solver hands back to `run`
+pub(crate) struct Fit {
+    /// the best param, flattened and `param_len` long
+    pub param: DVector<f64>,
+    /// the cost at `param`
+    pub cost: f64,
+    /// the cost after each iteration
+    pub cost_history: Vec<f64>,
+    /// how many iterations (epochs for sgd) ran
+    pub iterations: u64,
+    /// why the solver stopped
+    pub termination: TerminationReason,
+    /// evaluations by kind, keyed like argmin's (`cost_count`, `gradient_count`)
+    pub func_counts: HashMap<String, u64>,
+}
+
+///
+/// The solver's bookkeeping from `run_verbose`. Batch gradients count like
+/// full ones under sgd, and the penalized gradients of a ridge fit count too.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolverStats {
+    pub cost_count: u64,
+    pub gradient_count: u64,
+    /// always 0 for LBFGS, which never forms the Hessian
+    pub hessian_count: u64,
+    /// the likelihood at the reported betas; the `Findings::final_nll`
+    pub final_cost: f64,
+    /// every count the solver kept, e.g. argmin's
+    pub func_counts: HashMap<String, u64>,
+}
+
+impl Fit {
+    fn count(&self, kind: &str) -> u64 {
+        self.func_counts.get(kind).copied().unwrap_or_default()
+    }
+    /// stopped by `Cfg::max_duration` rather than converging or `max_iters`
+    pub fn timed_out(&self) -> bool {
+        matches!(&self.termination, TerminationReason::SolverExit(reason) if reason == TIMED_OUT)
+    }
+}
+
+/// The evaluations of the hand-rolled solvers, as argmin counts its own
+#[derive(Default)]
+struct Counts {
+    cost: u64,
+    gradient: u64,
+    hessian: u64,
+}
+
+impl Counts {
+    fn into_map(self) -> HashMap<String, u64> {
+        HashMap::from([
+            ("cost_count".to_string(), self.cost),
+            ("gradient_count".to_string(), self.gradient),
+            ("hessian_count".to_string(), self.hessian),
+        ])
+    }
+}
+
+/// Zeros, or `Cfg::warm_start` when it fits the param
+fn initial_param<D: Design>(objective: &D, cfg: &Cfg) -> Result<DVector<f64>> {
+    match &cfg.warm_start {
+        None => Ok(DVector::zeros(objective.param_len())),
+        Some(betas) if betas.len() == objective.param_len() => Ok(betas.clone()),
+        Some(betas) => Err(eyre!(
+            "The warm start has {} betas; the objective needs {}",
+            betas.len(),
+            objective.param_len()
+        )),
+    }
+}
 
+/// The LBFGS fit shared by every objective
+pub(crate) fn minimize<'a, D>(objective: &'a D, cfg: &Cfg, monitor: Option<Monitor>) -> Result<Fit>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
     // Define initial parameter vector
-    let init_param: DVector<f64> = DVector::from_vec(vec![0f64; p]);
+    let init_param = initial_param(objective, cfg)?;
 
     // Set condition
     let cond = ArmijoCondition::new(0.5).map_err(|e| eyre!("Failed condition {}", e))?;
@@ -114,28 +563,631 @@ pub fn run<'a>(
         .map_err(|e| eyre!("Failed linesearch {}", e))?;
 
     // Set up solver
-    let solver = LBFGS::new(linesearch, 7);
+    let lbfgs = LBFGS::new(linesearch, 7)
+        .with_tolerance_grad(cfg.gtol)
+        .map_err(|e| eyre!("Invalid gtol: {}", e))?;
+    // orthant-wise steps once there is an l1 penalty
+    let lbfgs = if cfg.l1 > 0.0 {
+        lbfgs
+            .with_l1_regularization(cfg.l1)
+            .map_err(|e| eyre!("Invalid l1: {}", e))?
+    } else {
+        lbfgs
+    };
+    let solver = Deadline::new(lbfgs, cfg.max_duration, monitor);
 
     // Run solver
+    let cost_history = CostHistory::default();
     let res = Executor::new(objective, solver)
-        .configure(|state| state.param(init_param).max_iters(max_iters));
+        .configure(|state| state.param(init_param).max_iters(cfg.max_iters))
+        .add_observer(cost_history.clone(), ObserverMode::Always);
+    #[cfg(feature = "progress")]
+    let progress = cfg
+        .progress
+        .then(|| Progress::new(cfg.max_iters, cfg.logging));
+    #[cfg(feature = "progress")]
+    let res = match &progress {
+        Some(progress) => res.add_observer(progress.clone(), ObserverMode::Always),
+        None => res,
+    };
+    // with a progress bar, the bar prints the log lines
+    #[cfg(feature = "progress")]
+    let logging = cfg.logging && progress.is_none();
+    #[cfg(not(feature = "progress"))]
+    let logging = cfg.logging;
     let res = if logging {
         res.add_observer(SlogLogger::term(), ObserverMode::Always)
     } else {
         res
     };
+    let res = match &cfg.on_iteration {
+        Some(callback) => res.add_observer(callback.clone(), ObserverMode::Always),
+        None => res,
+    };
     let res = res.run().map_err(|e| eyre!("Result failed: {}", e))?;
+    #[cfg(feature = "progress")]
+    if let Some(progress) = &progress {
+        progress.finish();
+    }
 
-    let w: &DVector<f64> = &res.state().best_param.as_ref().unwrap();
+    let w: DVector<f64> = res
+        .state()
+        .best_param
+        .clone()
+        .ok_or_else(|| eyre!("Solver finished without a best param"))?;
 
     // std::thread::sleep(std::time::Duration::from_secs(1));
 
     event!(Level::INFO, "🏁 shape: {:?}", w.shape());
 
-    Ok(Findings {
-        all_betas: w.rows(0, p).into_owned(),
-        coefficients: w.rows(0, p - 1).into_owned(),
-        intercept: w[p - 1],
-        objective,
+    let termination = match &res.state().termination_status {
+        TerminationStatus::Terminated(reason) => reason.clone(),
+        TerminationStatus::NotTerminated => TerminationReason::default(),
+    };
+
+    Ok(Fit {
+        param: w,
+        cost: res.state().best_cost,
+        cost_history: cost_history.take(),
+        iterations: res.state().iter,
+        termination,
+        func_counts: res.state().get_func_counts().clone(),
+    })
+}
+
+/// Newton stops once no beta moves by more than this
+const NEWTON_STEP_TOLERANCE: f64 = 1e-10;
+
+///
+/// Newton-Raphson: solve `H delta = g` for the step at each iteration. Errors
+/// when the Hessian is singular (constant or collinear features) rather than
+/// quietly stabilizing it.
+///
+pub(crate) fn newton<'a, D>(
+    objective: &'a D,
+    cfg: &Cfg,
+    mut monitor: Option<Monitor>,
+) -> Result<Fit>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    let mut w = initial_param(objective, cfg)?;
+    let mut cost = objective
+        .cost(&w)
+        .map_err(|e| eyre!("Result failed: {}", e))?;
+    let mut cost_history = Vec::new();
+    let start = Instant::now();
+    let mut termination = TerminationReason::MaxItersReached;
+    let mut counts = Counts::default();
+    counts.cost += 1;
+
+    for iter in 0..cfg.max_iters {
+        if expired(start, cfg.max_duration) {
+            termination = TerminationReason::SolverExit(TIMED_OUT.to_string());
+            break;
+        }
+        let gradient = objective
+            .gradient(&w)
+            .map_err(|e| eyre!("Result failed: {}", e))?;
+        counts.gradient += 1;
+        if gradient.norm() < cfg.gtol {
+            termination = TerminationReason::SolverConverged;
+            break;
+        }
+        counts.hessian += 1;
+        let delta = objective
+            .hessian(&w)?
+            .cholesky()
+            .ok_or_else(|| eyre!("Hessian is singular; check for constant or collinear features"))?
+            .solve(&gradient);
+        w -= &delta;
+
+        cost = objective
+            .cost(&w)
+            .map_err(|e| eyre!("Result failed: {}", e))?;
+        counts.cost += 1;
+        cost_history.push(cost);
+        if cfg.logging {
+            event!(Level::INFO, "🔁 newton iter: {} cost: {}", iter, cost);
+        }
+        if let Some(callback) = &cfg.on_iteration {
+            (callback.0.lock().unwrap())(iter, cost);
+        }
+
+        if delta.amax() < NEWTON_STEP_TOLERANCE {
+            termination = TerminationReason::SolverConverged;
+            break;
+        }
+        if monitor.as_mut().is_some_and(|monitor| monitor(&w)) {
+            termination = TerminationReason::SolverExit(EARLY_STOPPED.to_string());
+            break;
+        }
+    }
+
+    event!(Level::INFO, "🏁 shape: {:?}", w.shape());
+
+    Ok(Fit {
+        param: w,
+        cost,
+        iterations: cost_history.len() as u64,
+        cost_history,
+        termination,
+        func_counts: counts.into_map(),
     })
 }
+
+///
+/// Mini-batch sgd with optional momentum. Each epoch visits every record once in
+/// a fresh random order; the step is the mean gradient of the batch. The cost
+/// is recorded (and reported to the observers) once per epoch.
+///
+pub(crate) fn sgd<'a, D>(
+    objective: &'a D,
+    cfg: &Cfg,
+    mut monitor: Option<Monitor>,
+    batch_size: usize,
+    learning_rate: f64,
+    epochs: u64,
+    momentum: f64,
+) -> Result<Fit>
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    if batch_size == 0 {
+        return Err(eyre!("The sgd batch size must be at least 1"));
+    }
+    if !(0.0..1.0).contains(&momentum) {
+        return Err(eyre!(
+            "The sgd momentum must be in [0, 1), got {}",
+            momentum
+        ));
+    }
+
+    let mut rng = cfg.rng();
+    let mut rows: Vec<usize> = (0..objective.record_count()).collect();
+    let mut w = initial_param(objective, cfg)?;
+    let mut velocity: DVector<f64> = DVector::zeros(objective.param_len());
+    let mut cost_history = Vec::new();
+    let start = Instant::now();
+    let mut termination = TerminationReason::MaxItersReached;
+    let mut counts = Counts::default();
+
+    'epochs: for epoch in 0..epochs {
+        rows.shuffle(&mut rng);
+        for batch in rows.chunks(batch_size) {
+            if expired(start, cfg.max_duration) {
+                termination = TerminationReason::SolverExit(TIMED_OUT.to_string());
+                break 'epochs;
+            }
+            let gradient = objective.batch_gradient(&w, batch)? / batch.len() as f64;
+            counts.gradient += 1;
+            velocity = velocity * momentum - gradient * learning_rate;
+            w += &velocity;
+        }
+
+        let cost = objective
+            .cost(&w)
+            .map_err(|e| eyre!("Result failed: {}", e))?;
+        counts.cost += 1;
+        cost_history.push(cost);
+        if cfg.logging {
+            event!(Level::INFO, "🔁 sgd epoch: {} cost: {}", epoch, cost);
+        }
+        if let Some(callback) = &cfg.on_iteration {
+            (callback.0.lock().unwrap())(epoch, cost);
+        }
+        let gradient = objective
+            .gradient(&w)
+            .map_err(|e| eyre!("Result failed: {}", e))?;
+        counts.gradient += 1;
+        if gradient.norm() < cfg.gtol {
+            termination = TerminationReason::SolverConverged;
+            break;
+        }
+        if monitor.as_mut().is_some_and(|monitor| monitor(&w)) {
+            termination = TerminationReason::SolverExit(EARLY_STOPPED.to_string());
+            break;
+        }
+    }
+
+    // a timeout mid-epoch leaves w past the last recorded cost
+    let cost = match cost_history.last() {
+        Some(cost)
+            if matches!(
+                termination,
+                TerminationReason::MaxItersReached | TerminationReason::SolverConverged
+            ) =>
+        {
+            *cost
+        }
+        _ => {
+            counts.cost += 1;
+            objective
+                .cost(&w)
+                .map_err(|e| eyre!("Result failed: {}", e))?
+        }
+    };
+
+    event!(Level::INFO, "🏁 shape: {:?}", w.shape());
+
+    Ok(Fit {
+        param: w,
+        cost,
+        iterations: cost_history.len() as u64,
+        cost_history,
+        termination,
+        func_counts: counts.into_map(),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use nalgebra::base::DMatrix;
+
+    /// 20 records of one predictor (then the intercept) that overlap across the classes
+    pub(crate) fn toy() -> Objective {
+        let y = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 0.0,
+            1.0, 1.0, 1.0,
+        ];
+        let x = DMatrix::from_fn(y.len(), 2, |i, j| match j {
+            0 => i as f64 / 4.0 - 2.5,
+            _ => 1.0,
+        });
+        Objective::new(x, DVector::from_row_slice(&y))
+    }
+
+    #[test]
+    fn the_cost_history_records_every_iteration() {
+        let objective = toy();
+        for solver in [SolverKind::Lbfgs, SolverKind::Newton] {
+            let findings = run(&objective, CfgBuilder::new().solver(solver).build()).unwrap();
+            let history = &findings.cost_history;
+            assert_eq!(history.len() as u64, findings.iterations);
+            assert!(history.windows(2).all(|pair| pair[1] <= pair[0]));
+            assert!((history.last().unwrap() - findings.final_nll).abs() < 1e-9);
+            // from the cost at the zero start, 20 ln 2
+            assert!(history[0] < 20.0 * std::f64::consts::LN_2);
+        }
+    }
+
+    #[test]
+    fn the_iteration_callback_sees_each_cost_in_order() {
+        let objective = toy();
+        for solver in [SolverKind::Lbfgs, SolverKind::Newton] {
+            let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            let sink = seen.clone();
+            let cfg = CfgBuilder::new()
+                .solver(solver)
+                .on_iteration(move |iter, cost| sink.lock().unwrap().push((iter, cost)))
+                .build();
+            let findings = run(&objective, cfg).unwrap();
+            let seen = seen.lock().unwrap();
+            let costs: Vec<f64> = seen.iter().map(|(_, cost)| *cost).collect();
+            assert_eq!(costs, findings.cost_history);
+            assert!(seen.windows(2).all(|pair| pair[1].0 == pair[0].0 + 1));
+        }
+    }
+
+    #[test]
+    fn newton_reaches_the_lbfgs_optimum_in_fewer_iterations() {
+        let objective = toy();
+        let lbfgs = run(&objective, CfgBuilder::new().build()).unwrap();
+        let newton = run(
+            &objective,
+            CfgBuilder::new().solver(SolverKind::Newton).build(),
+        )
+        .unwrap();
+        assert!((newton.all_betas.clone() - &lbfgs.all_betas).amax() < 1e-4);
+        assert!((newton.final_nll - lbfgs.final_nll).abs() < 1e-8);
+        assert!(newton.iterations < lbfgs.iterations);
+        assert!(newton.gradient_norm < 1e-8);
+    }
+
+    #[test]
+    fn perfect_separation_is_flagged() {
+        let objective = toy();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        assert!(!findings.separation_warning);
+
+        let x = DMatrix::from_fn(8, 2, |i, j| if j == 0 { i as f64 - 3.5 } else { 1.0 });
+        let y = DVector::from_fn(8, |i, _| if i >= 4 { 1.0 } else { 0.0 });
+        let separable = Objective::new(x, y);
+        let findings = run(&separable, CfgBuilder::new().max_iters(200).build()).unwrap();
+        assert!(findings.separation_warning);
+        assert!(findings.coefficients[0] > SEPARATION_BETA_LIMIT);
+    }
+
+    #[test]
+    fn sgd_approaches_the_lbfgs_optimum() {
+        let objective = toy();
+        let lbfgs = run(&objective, CfgBuilder::new().build()).unwrap();
+        let sgd = SolverKind::Sgd {
+            batch_size: 5,
+            learning_rate: 0.05,
+            epochs: 2000,
+            momentum: 0.5,
+        };
+        let cfg = CfgBuilder::new().solver(sgd).seed(7).build();
+        let findings = run(&objective, cfg).unwrap();
+        assert!((findings.all_betas - &lbfgs.all_betas).amax() < 0.1);
+        assert!(findings.final_nll - lbfgs.final_nll < 1e-2);
+        // seeded, the batches and so the betas repeat
+        let again = run(&objective, CfgBuilder::new().solver(sgd).seed(7).build()).unwrap();
+        assert_eq!(again.final_nll, findings.final_nll);
+    }
+
+    #[test]
+    fn sgd_rejects_an_empty_batch_and_out_of_range_momentum() {
+        let objective = toy();
+        for (batch_size, momentum) in [(0, 0.0), (5, 1.0), (5, -0.1)] {
+            let sgd = SolverKind::Sgd {
+                batch_size,
+                learning_rate: 0.05,
+                epochs: 1,
+                momentum,
+            };
+            assert!(run(&objective, CfgBuilder::new().solver(sgd).build()).is_err());
+        }
+    }
+
+    #[test]
+    fn an_expired_deadline_times_the_fit_out() {
+        let objective = toy();
+        for solver in [SolverKind::Lbfgs, SolverKind::Newton] {
+            let cfg = CfgBuilder::new()
+                .solver(solver)
+                .max_duration(std::time::Duration::ZERO)
+                .build();
+            let findings = run(&objective, cfg).unwrap();
+            assert!(findings.timed_out);
+            assert_eq!(
+                findings.termination,
+                TerminationReason::SolverExit(TIMED_OUT.to_string())
+            );
+            let findings = run(&objective, CfgBuilder::new().solver(solver).build()).unwrap();
+            assert!(!findings.timed_out);
+        }
+    }
+
+    #[test]
+    fn the_termination_reason_and_iterations_are_reported() {
+        let objective = toy();
+        let capped = run(&objective, CfgBuilder::new().max_iters(2).build()).unwrap();
+        assert_eq!(capped.termination, TerminationReason::MaxItersReached);
+        assert_eq!(capped.iterations, 2);
+        let newton = CfgBuilder::new().solver(SolverKind::Newton).build();
+        let converged = run(&objective, newton).unwrap();
+        assert_eq!(converged.termination, TerminationReason::SolverConverged);
+        assert!(converged.iterations > 0 && converged.iterations < 10);
+    }
+
+    #[test]
+    fn check_gradient_agrees_with_central_differences() {
+        let objective = toy();
+        for w in [[0.0, 0.0], [0.8, -0.3], [-2.0, 1.5]] {
+            let diff = check_gradient(&objective, &DVector::from_row_slice(&w), 1e-6).unwrap();
+            assert!(diff < 1e-5, "{diff} at {w:?}");
+        }
+    }
+
+    #[test]
+    fn check_gradient_picks_up_the_truncation_error_of_a_coarse_step() {
+        let objective = toy();
+        let w = DVector::from_row_slice(&[0.8, -0.3]);
+        let fine = check_gradient(&objective, &w, 1e-5).unwrap();
+        let coarse = check_gradient(&objective, &w, 0.5).unwrap();
+        assert!(coarse > 100.0 * fine, "{coarse} vs {fine}");
+    }
+
+    #[test]
+    fn softplus_is_stable_at_both_extremes() {
+        assert_eq!(softplus(0.0), std::f64::consts::LN_2);
+        assert!((softplus(1.0) - (1.0 + 1f64.exp()).ln()).abs() < 1e-15);
+        assert_eq!(softplus(800.0), 800.0);
+        assert!((softplus(-800.0)).abs() < 1e-300);
+        assert!((softplus(-40.0) - (-40f64).exp()).abs() < 1e-30);
+    }
+
+    #[test]
+    fn neg_log_likelihood_stays_finite_for_confident_mistakes() {
+        let z = DVector::from_row_slice(&[1000.0, -1000.0, 0.0]);
+        let y = DVector::from_row_slice(&[0.0, 1.0, 1.0]);
+        // each mistake costs |z|; the undecided record ln 2
+        let expected = 2000.0 + std::f64::consts::LN_2;
+        assert!((neg_log_likelihood(&z, &y, None) - expected).abs() < 1e-9);
+
+        let weights = DVector::from_row_slice(&[0.5, 0.0, 2.0]);
+        let expected = 500.0 + 2.0 * std::f64::consts::LN_2;
+        assert!((neg_log_likelihood(&z, &y, Some(&weights)) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn the_sgd_batch_order_follows_the_seed() {
+        use rand::Rng;
+
+        let objective = toy();
+        let sgd = SolverKind::Sgd {
+            batch_size: 3,
+            learning_rate: 0.1,
+            epochs: 5,
+            momentum: 0.0,
+        };
+        let fit = |cfg: Cfg| run(&objective, cfg).unwrap().all_betas;
+        let seeded = || CfgBuilder::new().solver(sgd);
+        assert_eq!(fit(seeded().seed(1).build()), fit(seeded().seed(1).build()));
+        assert_ne!(fit(seeded().seed(1).build()), fit(seeded().seed(2).build()));
+        // unseeded runs draw fresh entropy
+        assert_ne!(fit(seeded().build()), fit(seeded().build()));
+
+        let cfg = seeded().seed(9).build();
+        assert_eq!(cfg.rng().gen::<u64>(), cfg.rng().gen::<u64>());
+    }
+
+    #[test]
+    fn gtol_sets_where_the_fit_counts_as_converged() {
+        let objective = toy();
+        let tight = run(&objective, CfgBuilder::new().build()).unwrap();
+        let loose = run(&objective, CfgBuilder::new().gtol(1e-2).build()).unwrap();
+        assert_eq!(loose.termination, TerminationReason::SolverConverged);
+        assert!(loose.gradient_norm < 1e-2);
+        assert!(tight.gradient_norm < 1.5e-8);
+        assert!(loose.iterations < tight.iterations);
+
+        // sgd checks once per epoch
+        let sgd = SolverKind::Sgd {
+            batch_size: 20,
+            learning_rate: 0.01,
+            epochs: 50,
+            momentum: 0.0,
+        };
+        let cfg = CfgBuilder::new().solver(sgd).gtol(1e3).seed(1).build();
+        let findings = run(&objective, cfg).unwrap();
+        assert_eq!(findings.termination, TerminationReason::SolverConverged);
+        assert_eq!(findings.iterations, 1);
+
+        assert!(run(&objective, CfgBuilder::new().gtol(-1.0).build()).is_err());
+    }
+
+    #[test]
+    fn run_verbose_counts_the_solver_evaluations() {
+        let objective = toy();
+        let (findings, stats) = run_verbose(&objective, CfgBuilder::new().build()).unwrap();
+        let plain = run(&objective, CfgBuilder::new().build()).unwrap();
+        assert_eq!(findings.all_betas, plain.all_betas);
+        assert_eq!(stats.final_cost, findings.final_nll);
+        assert_eq!(stats.hessian_count, 0);
+        assert!(stats.gradient_count >= findings.iterations);
+        assert!(stats.cost_count >= findings.iterations);
+        assert_eq!(stats.func_counts["gradient_count"], stats.gradient_count);
+
+        let newton = CfgBuilder::new().solver(SolverKind::Newton).build();
+        let (findings, stats) = run_verbose(&objective, newton).unwrap();
+        // one Hessian per Newton step
+        assert_eq!(stats.hessian_count, findings.iterations);
+        assert_eq!(stats.final_cost, findings.final_nll);
+    }
+
+    #[test]
+    fn check_penalties_names_the_conflict() {
+        let err = CfgBuilder::new()
+            .l1(0.5)
+            .build()
+            .check_penalties()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Lbfgs can't fit an l1 penalty, which isn't smooth at 0; use SolverKind::OwlQn"
+        );
+        let cfg = CfgBuilder::new()
+            .solver(SolverKind::OwlQn)
+            .l1(f64::NAN)
+            .build();
+        assert_eq!(
+            cfg.check_penalties().unwrap_err().to_string(),
+            "The l1 penalty must be finite and >= 0; got NaN"
+        );
+        let cfg = CfgBuilder::new().solver(SolverKind::OwlQn).l1(0.5).build();
+        assert!(cfg.check_penalties().is_ok());
+        assert!(CfgBuilder::new().build().check_penalties().is_ok());
+    }
+
+    #[test]
+    fn the_gradient_matches_the_row_fold_it_replaced() {
+        // uneven values and enough rows that a wrong term would show
+        let x = DMatrix::from_fn(37, 4, |i, j| match j {
+            3 => 1.0,
+            _ => ((i * 7 + j * 13) % 11) as f64 / 3.0 - 1.7,
+        });
+        let y = DVector::from_fn(37, |i, _| ((i * 5) % 3 == 0) as u8 as f64);
+        let objective = Objective::new(x, y);
+
+        for w in [
+            vec![0.0, 0.0, 0.0, 0.0],
+            vec![0.3, -1.1, 0.7, 0.05],
+            vec![-2.5, 0.01, 4.0, -0.9],
+        ] {
+            let w = DVector::from_vec(w);
+            let residual = sigmoid_vec(&objective.log_odds(&w)) - &objective.y;
+            let fold = objective
+                .x
+                .row_iter()
+                .zip(&residual)
+                .fold(vec![0.0; 4], |acc, (xs, r)| {
+                    acc.into_iter()
+                        .zip(xs.iter())
+                        .map(|(a, x)| a + x * r)
+                        .collect()
+                });
+            let gradient = (&objective).gradient(&w).unwrap();
+            // tr_mul sums in its own order, so agreement is to rounding
+            let fold = DVector::from_vec(fold);
+            assert!(
+                (&gradient - &fold).amax() <= 1e-12 * fold.amax(),
+                "{} vs {}",
+                gradient,
+                fold
+            );
+        }
+    }
+
+    #[test]
+    fn an_l1_penalty_needs_owl_qn() {
+        let objective = toy();
+        assert!(run(&objective, CfgBuilder::new().l1(0.5).build()).is_err());
+        let newton = CfgBuilder::new().solver(SolverKind::Newton).l1(0.5).build();
+        assert!(run(&objective, newton).is_err());
+        assert!(run(
+            &objective,
+            CfgBuilder::new().solver(SolverKind::OwlQn).l1(-1.0).build()
+        )
+        .is_err());
+        assert!(run(
+            &objective,
+            CfgBuilder::new().solver(SolverKind::OwlQn).l1(0.5).build()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn owl_qn_shrinks_toward_zero_and_reports_the_subgradient() {
+        let objective = toy();
+        let plain = run(&objective, CfgBuilder::new().build()).unwrap();
+        let cfg = CfgBuilder::new()
+            .solver(SolverKind::OwlQn)
+            .l1(1.0)
+            .max_iters(500);
+        let lasso = run(&objective, cfg.build()).unwrap();
+        assert!(lasso.coefficients[0].abs() < plain.coefficients[0].abs());
+        assert!(
+            lasso.gradient_norm < 1e-3,
+            "gradient norm {}",
+            lasso.gradient_norm
+        );
+        // a penalty past the largest score zeroes every slope
+        let cfg = CfgBuilder::new().solver(SolverKind::OwlQn).l1(100.0);
+        let zeroed = run(&objective, cfg.build()).unwrap();
+        assert_eq!(zeroed.coefficients, DVector::zeros(1));
+        assert!(zeroed.gradient_norm < 1e-8);
+    }
+
+    #[test]
+    fn owl_qn_leaves_the_intercept_unpenalized() {
+        // 6 of the first 15 records are positive
+        let objective = toy().subset(&(0..15).collect::<Vec<_>>());
+        let cfg = || CfgBuilder::new().solver(SolverKind::OwlQn);
+        let zeroed = run(&objective, cfg().l1(100.0).build()).unwrap();
+        assert_eq!(zeroed.coefficients, DVector::zeros(1));
+        assert!((zeroed.intercept - (6.0f64 / 9.0).ln()).abs() < 1e-9);
+
+        // a negligible penalty leaves the unpenalized fit
+        let plain = run(&objective, CfgBuilder::new().gtol(1e-10).build()).unwrap();
+        let lasso = run(&objective, cfg().l1(1e-9).build()).unwrap();
+        assert!((&lasso.all_betas - &plain.all_betas).amax() < 1e-4);
+    }
+}
@@ -1,10 +1,13 @@
 use color_eyre::eyre::{eyre, Report, Result};
-use csv;
-use nalgebra::base::Scalar;
+use nalgebra::base::{DMatrix, Scalar};
 
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 use std::str::FromStr;
 
+use crate::models::hash_bucket;
+
 ///
 /// Returns dynamic matrix with target in the first position, and the
 /// placeholder for intercept in the last position.
@@ -20,17 +23,18 @@ where
     <N as FromStr>::Err: Send + Sync,
     <N as FromStr>::Err: std::error::Error,
 {
-    let mut reader = csv::ReaderBuilder::new()
-        .has_headers(with_headers)
-        .from_path(path)?;
+    let mut reader = reader(path, with_headers)?;
+    let width = reader.byte_headers()?.len();
 
     // stage the data on zero copy for Matrix
-    let bias_slot = vec![&[b'1']];
+    let bias_slot = [b"1"];
     //
     let staged_records: Vec<N> = reader
         .byte_records()
         .map(|record| -> Result<Vec<N>> {
-            let record: Vec<N> = record?
+            let record = record?;
+            check_width(&record, width, with_headers)?;
+            let record: Vec<N> = record
                 .iter()
                 .chain(bias_slot.iter().map(|&v| v.as_ref()))
                 .map(|value| {
@@ -47,8 +51,335 @@ where
         .collect();
 
     // feature count + intercept slot
-    let feature_count = reader.headers()?.len() + 1;
+    let feature_count = width + 1;
     let num_records = staged_records.len() / feature_count;
 
     Ok((staged_records, num_records))
 }
+
+///
+/// Same layout as `from_csv` (target first, intercept placeholder last) for files
+/// that approach the available memory. The file is read twice: the first pass
+/// only counts records, the second parses each record straight into a matrix
+/// allocated once at its final size. Peak memory is that matrix plus a single
+/// record buffer, where `from_csv` holds the staged rows, their flattened copy
+/// and then the matrix.
+///
+pub fn from_csv_streaming<P: AsRef<Path>>(path: P, with_headers: bool) -> Result<DMatrix<f64>> {
+    // first pass: count and validate
+    let mut record = csv::ByteRecord::new();
+    let mut counter = reader(path.as_ref(), with_headers)?;
+    let width = counter.byte_headers()?.len();
+    let mut num_records = 0;
+    while counter.read_byte_record(&mut record)? {
+        check_width(&record, width, with_headers)?;
+        num_records += 1;
+    }
+
+    // second pass: fill; feature count + intercept slot
+    let mut matrix = DMatrix::from_element(num_records, width + 1, 1.0);
+    let mut filler = reader(path.as_ref(), with_headers)?;
+    let mut i = 0;
+    while filler.read_byte_record(&mut record)? {
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        for (j, value) in record.iter().enumerate() {
+            matrix[(i, j)] = std::str::from_utf8(value)
+                .map_err(|e| eyre!("Line {}: error decoding utf8: {}", line, e))?
+                .parse()
+                .map_err(|e| eyre!("Line {}, field {}: {}", line, j + 1, e))?;
+        }
+        i += 1;
+    }
+
+    Ok(matrix)
+}
+
+///
+/// `from_csv` where the input `column` holds strings (a categorical with too
+/// many levels to one-hot): each value is hashed by `hash_bucket` into one of
+/// `width` 0/1 columns, which take the column's place. The target (column 0)
+/// can't be hashed.
+///
+pub fn from_csv_hashed<P: AsRef<Path>>(
+    path: P,
+    with_headers: bool,
+    column: usize,
+    width: usize,
+) -> Result<DMatrix<f64>> {
+    let mut reader = reader(path, with_headers)?;
+    let fields = reader.byte_headers()?.len();
+    if fields < 2 {
+        return Err(eyre!(
+            "A csv with {} columns has no predictor to hash",
+            fields
+        ));
+    }
+    if column == 0 || column >= fields {
+        return Err(eyre!(
+            "Column {} can't be hashed; the predictors are columns 1 to {}",
+            column,
+            fields - 1
+        ));
+    }
+    if width == 0 {
+        return Err(eyre!("Feature hashing needs at least 1 bucket"));
+    }
+
+    let row_width = fields - 1 + width + 1;
+    let mut staged_records: Vec<f64> = Vec::new();
+    let mut record = csv::ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        check_width(&record, fields, with_headers)?;
+        for (j, value) in record.iter().enumerate() {
+            let value =
+                std::str::from_utf8(value).map_err(|e| eyre!("Error decoding utf8: {}", e))?;
+            if j == column {
+                let bucket = hash_bucket(value, width);
+                staged_records.extend((0..width).map(|b| if b == bucket { 1.0 } else { 0.0 }));
+            } else {
+                staged_records.push(value.parse()?);
+            }
+        }
+        // intercept slot
+        staged_records.push(1.0);
+    }
+    if staged_records.is_empty() {
+        return Err(eyre!("The csv holds no records"));
+    }
+
+    Ok(DMatrix::from_row_slice(
+        staged_records.len() / row_width,
+        row_width,
+        &staged_records,
+    ))
+}
+
+///
+/// One value per line, optionally preceded by an id column; a non-empty `header`
+/// holds the column names written first.
+///
+pub fn write_column<P: AsRef<Path>>(
+    path: P,
+    values: &[f64],
+    ids: Option<&[String]>,
+    header: &[&str],
+) -> Result<()> {
+    if let Some(ids) = ids {
+        if ids.len() != values.len() {
+            return Err(eyre!("Got {} ids for {} values", ids.len(), values.len()));
+        }
+    }
+    let mut writer = csv::Writer::from_path(path)?;
+    if !header.is_empty() {
+        writer.write_record(header)?;
+    }
+    for (i, value) in values.iter().enumerate() {
+        match ids {
+            Some(ids) => writer.write_record([ids[i].as_str(), &value.to_string()])?,
+            None => writer.write_record([value.to_string()])?,
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+///
+/// Record by record from `path` to one value per line in `out_path`, holding a
+/// single record in memory; `score` sees each record's `width` fields parsed.
+/// A non-empty `header` is written first.
+///
+pub fn map_records<P, Q, F>(
+    path: P,
+    with_headers: bool,
+    out_path: Q,
+    width: usize,
+    header: &[&str],
+    mut score: F,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnMut(&[f64]) -> f64,
+{
+    let mut reader = reader(path, with_headers)?;
+    let mut writer = csv::Writer::from_path(out_path)?;
+    if !header.is_empty() {
+        writer.write_record(header)?;
+    }
+    let mut record = csv::ByteRecord::new();
+    let mut values = Vec::with_capacity(width);
+    while reader.read_byte_record(&mut record)? {
+        if record.len() != width {
+            let line = record.position().map(|p| p.line()).unwrap_or_default();
+            return Err(eyre!(
+                "Line {} has {} fields; expected {}",
+                line,
+                record.len(),
+                width
+            ));
+        }
+        values.clear();
+        for value in record.iter() {
+            values.push(
+                std::str::from_utf8(value)
+                    .map_err(|e| eyre!("Error decoding utf8: {}", e))?
+                    .parse()?,
+            );
+        }
+        writer.write_record([score(&values).to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+///
+/// The column names of a csv; positional (`0`, `1`, ...) without headers
+///
+pub fn column_names<P: AsRef<Path>>(path: P, with_headers: bool) -> Result<Vec<String>> {
+    let mut reader = reader(path, with_headers)?;
+    if with_headers {
+        Ok(reader.headers()?.iter().map(String::from).collect())
+    } else {
+        let width = reader.byte_headers()?.len();
+        Ok((0..width).map(|j| j.to_string()).collect())
+    }
+}
+
+///
+/// Ragged records are reported by [`check_width`] rather than by the csv crate.
+/// A `.gz` file is decompressed on the fly (with the `gzip` feature).
+///
+fn reader<P: AsRef<Path>>(path: P, with_headers: bool) -> Result<csv::Reader<Box<dyn Read>>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| eyre!("Failed to open {}: {}", path.display(), e))?;
+    let source: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+        gzip(file)?
+    } else {
+        Box::new(file)
+    };
+    Ok(csv::ReaderBuilder::new()
+        .has_headers(with_headers)
+        .flexible(true)
+        .from_reader(source))
+}
+
+#[cfg(feature = "gzip")]
+fn gzip(file: File) -> Result<Box<dyn Read>> {
+    Ok(Box::new(flate2::read::GzDecoder::new(
+        std::io::BufReader::new(file),
+    )))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip(_file: File) -> Result<Box<dyn Read>> {
+    Err(eyre!("Reading a .gz csv requires the gzip feature"))
+}
+
+///
+/// Every record must be as wide as the header or, without headers, the first row
+///
+fn check_width(record: &csv::ByteRecord, width: usize, with_headers: bool) -> Result<()> {
+    if record.len() == width {
+        return Ok(());
+    }
+    let line = record.position().map(|p| p.line()).unwrap_or_default();
+    Err(eyre!(
+        "Line {} has {} fields but the {} has {}",
+        line,
+        record.len(),
+        if with_headers { "header" } else { "first row" },
+        width
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_csv(name: &str, contents: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("matrix-csv-{}-{}.csv", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn from_csv_hashed_sets_one_bucket_per_record() {
+        let path = write_csv("hashed", "y,city,a\n1,paris,2\n0,oslo,3\n");
+        let matrix = from_csv_hashed(&path, true, 1, 4).unwrap();
+        // target, 4 buckets, a, intercept
+        assert_eq!(matrix.shape(), (2, 7));
+        for (i, city) in ["paris", "oslo"].iter().enumerate() {
+            let buckets: Vec<f64> = (1..5).map(|j| matrix[(i, j)]).collect();
+            let mut expected = vec![0.0; 4];
+            expected[hash_bucket(city, 4)] = 1.0;
+            assert_eq!(buckets, expected);
+        }
+        assert_eq!(matrix[(1, 5)], 3.0);
+        assert_eq!(matrix[(1, 6)], 1.0);
+    }
+
+    #[test]
+    fn column_names_are_the_headers_or_positions() {
+        let path = write_csv("column-names", "y,a,b\n1,2,3\n");
+        assert_eq!(column_names(&path, true).unwrap(), vec!["y", "a", "b"]);
+        assert_eq!(column_names(&path, false).unwrap(), vec!["0", "1", "2"]);
+    }
+
+    #[test]
+    fn from_csv_hashed_rejects_an_empty_file() {
+        let empty = write_csv("hashed-empty", "");
+        assert!(from_csv_hashed(&empty, false, 1, 4).is_err());
+        assert!(from_csv_hashed(&empty, true, 1, 4).is_err());
+        let header_only = write_csv("hashed-header-only", "y,city\n");
+        assert!(from_csv_hashed(&header_only, true, 1, 4).is_err());
+    }
+
+    #[test]
+    fn a_ragged_record_is_reported_by_line() {
+        let short = write_csv("ragged-short", "y,a,b\n1,2,3\n0,4\n");
+        let message = from_csv::<_, f64>(&short, true).unwrap_err().to_string();
+        assert_eq!(message, "Line 3 has 2 fields but the header has 3");
+        let long = write_csv("ragged-long", "1,2\n0,4,5\n");
+        let message = from_csv::<_, f64>(&long, false).unwrap_err().to_string();
+        assert_eq!(message, "Line 2 has 3 fields but the first row has 2");
+        let even = write_csv("ragged-even", "1,2\n0,4\n");
+        let (staged, rows) = from_csv::<_, f64>(&even, false).unwrap();
+        assert_eq!((staged, rows), (vec![1.0, 2.0, 1.0, 0.0, 4.0, 1.0], 2));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn a_gz_csv_reads_like_the_plain_one() {
+        use std::io::Write;
+
+        let contents = "y,a\n1,2.5\n0,3\n";
+        let plain = write_csv("plain", contents);
+        let path =
+            std::env::temp_dir().join(format!("matrix-csv-gz-{}.csv.gz", std::process::id()));
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(contents.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let (gz, rows) = from_csv::<_, f64>(&path, true).unwrap();
+        assert_eq!(rows, 2);
+        assert_eq!(gz, from_csv::<_, f64>(&plain, true).unwrap().0);
+        assert_eq!(gz, [1.0, 2.5, 1.0, 0.0, 3.0, 1.0]);
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn a_gz_csv_needs_the_gzip_feature() {
+        let path = write_csv("needs-gzip", "");
+        let gz = path.with_extension("gz");
+        std::fs::rename(&path, &gz).unwrap();
+        let err = from_csv::<_, f64>(&gz, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Reading a .gz csv requires the gzip feature"
+        );
+    }
+}
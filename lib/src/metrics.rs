@@ -0,0 +1,599 @@
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::DVector;
+use rand::Rng;
+
+use std::fmt;
+
+use crate::auc_score::auc_score;
+use crate::configurations::Cfg;
+use crate::inference::validate_level;
+use crate::models::check_binary;
+
+///
+/// Mean squared error between the predicted probabilities and the 0/1 outcomes,
+/// `mean((p - y)^2)`; 0 is perfect, 0.25 is a constant 0.5 on balanced data.
+///
+pub fn brier_score(y_true: &DVector<f64>, y_prob: &DVector<f64>) -> Result<f64> {
+    check_lengths(y_true, y_prob)?;
+    if y_true.is_empty() {
+        return Err(eyre!("The brier score needs at least one prediction"));
+    }
+    Ok((y_prob - y_true).norm_squared() / y_true.len() as f64)
+}
+
+/// Probabilities are clipped this far from 0 and 1 so a confident miss costs
+/// a large, finite loss
+pub(crate) const LOG_LOSS_EPS: f64 = 1e-15;
+
+///
+/// Mean negative log-likelihood of the 0/1 outcomes under the predicted
+/// probabilities, `-mean(y ln p + (1 - y) ln(1 - p))`; 0 is perfect, `ln 2` is
+/// a constant 0.5.
+///
+pub fn log_loss(y_true: &DVector<f64>, y_prob: &DVector<f64>) -> Result<f64> {
+    check_lengths(y_true, y_prob)?;
+    check_binary(y_true)?;
+    if y_true.is_empty() {
+        return Err(eyre!("The log loss needs at least one prediction"));
+    }
+    let total: f64 = y_true
+        .iter()
+        .zip(y_prob.iter())
+        .map(|(y, p)| {
+            let p = p.clamp(LOG_LOSS_EPS, 1.0 - LOG_LOSS_EPS);
+            -(y * p.ln() + (1.0 - y) * (1.0 - p).ln())
+        })
+        .sum();
+    Ok(total / y_true.len() as f64)
+}
+
+///
+/// ROC curve as `(false positive rate, true positive rate)` points, one per
+/// distinct score from the highest down, starting at `(0, 0)` and ending at
+/// `(1, 1)`. Tied scores move both rates in a single step.
+///
+pub fn roc_curve(y_true: &DVector<f64>, y_prob: &DVector<f64>) -> Result<Vec<(f64, f64)>> {
+    check_lengths(y_true, y_prob)?;
+    check_binary(y_true)?;
+    let pos = y_true.sum();
+    let neg = y_true.len() as f64 - pos;
+    if pos == 0.0 || neg == 0.0 {
+        return Err(eyre!(
+            "The roc curve needs both classes, got {} positive and {} negative",
+            pos,
+            neg
+        ));
+    }
+
+    let mut order: Vec<(f64, f64)> = y_prob.iter().copied().zip(y_true.iter().copied()).collect();
+    order.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut curve = vec![(0.0, 0.0)];
+    let (mut tp, mut fp) = (0.0, 0.0);
+    for (i, (score, yi)) in order.iter().enumerate() {
+        tp += yi;
+        fp += 1.0 - yi;
+        if order.get(i + 1).is_none_or(|next| next.0 != *score) {
+            curve.push((fp / neg, tp / pos));
+        }
+    }
+    Ok(curve)
+}
+
+///
+/// Area under the ROC curve up to `max_fpr` (in `(0, 1]`), divided by `max_fpr`
+/// so a perfect ranking scores 1; at `max_fpr = 1` it is the full AUC.
+///
+pub fn partial_auc(y_true: &DVector<f64>, y_prob: &DVector<f64>, max_fpr: f64) -> Result<f64> {
+    if !(max_fpr > 0.0 && max_fpr <= 1.0) {
+        return Err(eyre!("max_fpr must be in (0, 1], got {}", max_fpr));
+    }
+    let curve = roc_curve(y_true, y_prob)?;
+
+    let mut area = 0.0;
+    for pair in curve.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if x0 >= max_fpr {
+            break;
+        }
+        // the segment crossing max_fpr is cut there
+        let (x1, y1) = if x1 > max_fpr {
+            (max_fpr, y0 + (y1 - y0) * (max_fpr - x0) / (x1 - x0))
+        } else {
+            (x1, y1)
+        };
+        area += (x1 - x0) * (y0 + y1) / 2.0;
+    }
+    Ok(area / max_fpr)
+}
+
+///
+/// Average precision, the area under the precision-recall curve as the step sum
+/// `sum (R_k - R_k-1) * P_k` over the distinct scores from the highest down.
+/// Unlike the ROC AUC it is dominated by the positives, so it exposes a model
+/// that ranks a rare class poorly; a random ranking scores the base rate.
+///
+pub fn average_precision(y_true: &DVector<f64>, y_prob: &DVector<f64>) -> Result<f64> {
+    check_lengths(y_true, y_prob)?;
+    check_binary(y_true)?;
+    let pos = y_true.sum();
+    if pos == 0.0 {
+        return Err(eyre!(
+            "Average precision is undefined without positive outcomes"
+        ));
+    }
+
+    let mut order: Vec<(f64, f64)> = y_prob.iter().copied().zip(y_true.iter().copied()).collect();
+    order.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let (mut tp, mut recall, mut area) = (0.0, 0.0, 0.0);
+    for (i, (score, yi)) in order.iter().enumerate() {
+        tp += yi;
+        if order.get(i + 1).is_none_or(|next| next.0 != *score) {
+            let precision = tp / (i + 1) as f64;
+            area += (tp / pos - recall) * precision;
+            recall = tp / pos;
+        }
+    }
+    Ok(area)
+}
+
+fn check_lengths(y_true: &DVector<f64>, y_prob: &DVector<f64>) -> Result<()> {
+    if y_true.len() == y_prob.len() {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "Got {} predictions for {} outcomes",
+            y_prob.len(),
+            y_true.len()
+        ))
+    }
+}
+
+///
+/// Percentile bootstrap interval of the AUC at `level` (e.g. 0.95): resample the
+/// rows with replacement `n_boot` times and take the empirical quantiles of the
+/// recomputed AUCs. Resamples that miss a class are skipped. The resampling
+/// follows `cfg`'s seed.
+///
+pub fn auc_bootstrap_ci(
+    y_true: &DVector<f64>,
+    y_prob: &DVector<f64>,
+    n_boot: usize,
+    level: f64,
+    cfg: &Cfg,
+) -> Result<(f64, f64)> {
+    check_lengths(y_true, y_prob)?;
+    validate_level(level)?;
+
+    let n = y_true.len();
+    let mut rng = cfg.rng();
+    let mut aucs: Vec<f64> = Vec::with_capacity(n_boot);
+    for _ in 0..n_boot {
+        let rows: Vec<usize> = (0..n).map(|_| rng.gen_range(0..n)).collect();
+        if let Ok(auc) = auc_score(&y_true.select_rows(&rows), &y_prob.select_rows(&rows)) {
+            aucs.push(auc);
+        }
+    }
+    if aucs.is_empty() {
+        return Err(eyre!(
+            "No bootstrap resample held both classes; check y_true and n_boot"
+        ));
+    }
+    aucs.sort_by(f64::total_cmp);
+
+    let alpha = (1.0 - level) / 2.0;
+    Ok((quantile(&aucs, alpha), quantile(&aucs, 1.0 - alpha)))
+}
+
+/// Linear interpolation between the closest ranks of the sorted `values`
+fn quantile(values: &[f64], q: f64) -> f64 {
+    let position = q * (values.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    values[lower] + (values[upper] - values[lower]) * (position - lower as f64)
+}
+
+///
+/// Counts of the 0/1 outcomes against the labels at a threshold; a score above
+/// the threshold is labelled 1, as in `CfgPredict`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConfusionMatrix {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+}
+
+impl ConfusionMatrix {
+    pub fn total(&self) -> usize {
+        self.true_positives + self.false_positives + self.true_negatives + self.false_negatives
+    }
+    /// `tp / (tp + fp)`; 1 when nothing is labelled 1, as at the end of a PR curve
+    pub fn precision(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+            1.0,
+        )
+    }
+    /// `tp / (tp + fn)`, the true positive rate; 0 without positives
+    pub fn recall(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+            0.0,
+        )
+    }
+    /// `tn / (tn + fp)`, the true negative rate; 0 without negatives
+    pub fn specificity(&self) -> f64 {
+        ratio(
+            self.true_negatives,
+            self.true_negatives + self.false_positives,
+            0.0,
+        )
+    }
+    /// harmonic mean of precision and recall; 0 when both are
+    pub fn f1(&self) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+    pub fn accuracy(&self) -> f64 {
+        ratio(self.true_positives + self.true_negatives, self.total(), 0.0)
+    }
+}
+
+/// `num / den`, or `empty` when `den` is 0
+fn ratio(num: usize, den: usize, empty: f64) -> f64 {
+    if den == 0 {
+        empty
+    } else {
+        num as f64 / den as f64
+    }
+}
+
+/// The confusion matrix of `y_prob` labelled at `threshold`
+pub fn confusion_matrix(
+    y_true: &DVector<f64>,
+    y_prob: &DVector<f64>,
+    threshold: f64,
+) -> Result<ConfusionMatrix> {
+    Ok(threshold_sweep(y_true, y_prob, &[threshold])?[0].1)
+}
+
+///
+/// The confusion matrix at each of `thresholds`, in the order given, from a
+/// single sort of the scores; each threshold then costs a binary search.
+///
+pub fn threshold_sweep(
+    y_true: &DVector<f64>,
+    y_prob: &DVector<f64>,
+    thresholds: &[f64],
+) -> Result<Vec<(f64, ConfusionMatrix)>> {
+    check_lengths(y_true, y_prob)?;
+    check_binary(y_true)?;
+
+    let mut order: Vec<(f64, f64)> = y_prob.iter().copied().zip(y_true.iter().copied()).collect();
+    order.sort_by(|a, b| a.0.total_cmp(&b.0));
+    // positives among the `i` lowest scores
+    let mut positives_below = Vec::with_capacity(order.len() + 1);
+    positives_below.push(0);
+    for (_, yi) in &order {
+        positives_below.push(positives_below.last().unwrap() + *yi as usize);
+    }
+    let (n, pos) = (order.len(), positives_below[order.len()]);
+
+    Ok(thresholds
+        .iter()
+        .map(|&threshold| {
+            let below = order.partition_point(|(score, _)| *score <= threshold);
+            let false_negatives = positives_below[below];
+            let true_positives = pos - false_negatives;
+            let false_positives = n - below - true_positives;
+            let matrix = ConfusionMatrix {
+                true_positives,
+                false_positives,
+                true_negatives: below - false_negatives,
+                false_negatives,
+            };
+            (threshold, matrix)
+        })
+        .collect())
+}
+
+///
+/// What `best_threshold` maximizes
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    F1,
+    /// `recall + specificity - 1`, the height of the ROC curve above chance
+    YoudenJ,
+    Accuracy,
+}
+
+impl MetricKind {
+    pub fn score(&self, matrix: &ConfusionMatrix) -> f64 {
+        match self {
+            MetricKind::F1 => matrix.f1(),
+            MetricKind::YoudenJ => matrix.recall() + matrix.specificity() - 1.0,
+            MetricKind::Accuracy => matrix.accuracy(),
+        }
+    }
+}
+
+///
+/// The threshold maximizing `metric`, searched over the distinct scores in
+/// `y_prob` (scores above the threshold are labelled 1). Ties go to the lowest
+/// such threshold.
+///
+pub fn best_threshold(
+    y_true: &DVector<f64>,
+    y_prob: &DVector<f64>,
+    metric: MetricKind,
+) -> Result<f64> {
+    let mut candidates: Vec<f64> = y_prob.iter().copied().collect();
+    candidates.sort_by(f64::total_cmp);
+    candidates.dedup();
+    if candidates.is_empty() {
+        return Err(eyre!("The threshold search needs at least one prediction"));
+    }
+
+    let mut best = (f64::NEG_INFINITY, candidates[0]);
+    for (threshold, matrix) in threshold_sweep(y_true, y_prob, &candidates)? {
+        let score = metric.score(&matrix);
+        if score > best.0 {
+            best = (score, threshold);
+        }
+    }
+    Ok(best.1)
+}
+
+///
+/// The evaluation numbers of a scored set in one place; see `metrics_report`
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricsReport {
+    pub auc: f64,
+    pub average_precision: f64,
+    pub log_loss: f64,
+    pub brier_score: f64,
+    /// the threshold the label based metrics below were taken at
+    pub threshold: f64,
+    pub accuracy: f64,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+impl fmt::Display for MetricsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"AUC: {:.4}
+average precision: {:.4}
+log loss: {:.4}
+Brier score: {:.4}
+at threshold {}:
+  accuracy: {:.4}
+  precision: {:.4}
+  recall: {:.4}
+  F1: {:.4}"#,
+            self.auc,
+            self.average_precision,
+            self.log_loss,
+            self.brier_score,
+            self.threshold,
+            self.accuracy,
+            self.precision,
+            self.recall,
+            self.f1,
+        )
+    }
+}
+
+///
+/// The ranking metrics of `y_prob` with the label metrics at `threshold`
+/// (scores above it are labelled 1)
+///
+pub fn metrics_report(
+    y_true: &DVector<f64>,
+    y_prob: &DVector<f64>,
+    threshold: f64,
+) -> Result<MetricsReport> {
+    let matrix = confusion_matrix(y_true, y_prob, threshold)?;
+    Ok(MetricsReport {
+        auc: auc_score(y_true, y_prob)?,
+        average_precision: average_precision(y_true, y_prob)?,
+        log_loss: log_loss(y_true, y_prob)?,
+        brier_score: brier_score(y_true, y_prob)?,
+        threshold,
+        accuracy: matrix.accuracy(),
+        precision: matrix.precision(),
+        recall: matrix.recall(),
+        f1: matrix.f1(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+
+    /// scikit-learn's running example: auc 0.75, average precision 5/6
+    fn scored() -> (DVector<f64>, DVector<f64>) {
+        (
+            DVector::from_vec(vec![0.0, 0.0, 1.0, 1.0]),
+            DVector::from_vec(vec![0.1, 0.4, 0.35, 0.8]),
+        )
+    }
+
+    #[test]
+    fn brier_score_is_the_mean_squared_error() {
+        let (y, p) = scored();
+        assert!((brier_score(&y, &p).unwrap() - 0.158_125).abs() < 1e-15);
+        assert_eq!(brier_score(&y, &y).unwrap(), 0.0);
+        assert_eq!(
+            brier_score(&y, &DVector::from_element(4, 0.5)).unwrap(),
+            0.25
+        );
+        assert!(brier_score(&y, &p.rows(0, 3).into_owned()).is_err());
+        assert!(brier_score(&DVector::zeros(0), &DVector::zeros(0)).is_err());
+    }
+
+    fn seeded(seed: u64) -> Cfg {
+        CfgBuilder::new().seed(seed).build()
+    }
+
+    #[test]
+    fn auc_bootstrap_ci_brackets_the_auc_and_repeats_with_the_seed() {
+        let y = DVector::from_fn(40, |i, _| (i % 2) as f64);
+        let p = DVector::from_fn(40, |i, _| {
+            ((i * 7) % 40) as f64 / 40.0 + 0.3 * (i % 2) as f64
+        });
+        let auc = auc_score(&y, &p).unwrap();
+        let (lower, upper) = auc_bootstrap_ci(&y, &p, 200, 0.9, &seeded(11)).unwrap();
+        assert!(lower < auc && auc < upper);
+        assert_eq!(
+            auc_bootstrap_ci(&y, &p, 200, 0.9, &seeded(11)).unwrap(),
+            (lower, upper)
+        );
+        let (lower_95, upper_95) = auc_bootstrap_ci(&y, &p, 200, 0.95, &seeded(11)).unwrap();
+        assert!(lower_95 <= lower && upper <= upper_95);
+        // a perfect ranking stays perfect in every resample
+        assert_eq!(
+            auc_bootstrap_ci(&y, &y, 50, 0.95, &seeded(1)).unwrap(),
+            (1.0, 1.0)
+        );
+        assert!(auc_bootstrap_ci(&y, &p, 10, 1.0, &seeded(1)).is_err());
+    }
+
+    #[test]
+    fn roc_curve_steps_through_the_scores_from_the_highest() {
+        let (y, p) = scored();
+        assert_eq!(
+            roc_curve(&y, &p).unwrap(),
+            [(0.0, 0.0), (0.0, 0.5), (0.5, 0.5), (0.5, 1.0), (1.0, 1.0)]
+        );
+        // a tie moves both rates at once
+        let tied = DVector::from_vec(vec![0.1, 0.5, 0.5, 0.8]);
+        assert_eq!(
+            roc_curve(&y, &tied).unwrap(),
+            [(0.0, 0.0), (0.0, 0.5), (0.5, 1.0), (1.0, 1.0)]
+        );
+        let one_class = DVector::from_element(4, 1.0);
+        assert!(roc_curve(&one_class, &p).is_err());
+    }
+
+    #[test]
+    fn partial_auc_is_normalized_by_max_fpr() {
+        let (y, p) = scored();
+        assert_eq!(partial_auc(&y, &p, 1.0).unwrap(), 0.75);
+        assert_eq!(partial_auc(&y, &p, 0.5).unwrap(), 0.5);
+        assert_eq!(partial_auc(&y, &p, 0.25).unwrap(), 0.5);
+        // the segment up to (0.5, 1) is cut at fpr 0.75: 0.25 * 1 added
+        assert!((partial_auc(&y, &p, 0.75).unwrap() - 0.5 / 0.75).abs() < 1e-12);
+
+        let perfect = DVector::from_vec(vec![0.1, 0.2, 0.8, 0.9]);
+        assert_eq!(partial_auc(&y, &perfect, 0.1).unwrap(), 1.0);
+        assert!(partial_auc(&y, &p, 0.0).is_err());
+        assert!(partial_auc(&y, &p, 1.5).is_err());
+    }
+
+    #[test]
+    fn threshold_sweep_counts_scores_above_each_threshold_as_positive() {
+        let (y, p) = scored();
+        let sweep = threshold_sweep(&y, &p, &[0.3, 0.5, 0.0, 0.8]).unwrap();
+        let counts: Vec<(f64, [usize; 4])> = sweep
+            .iter()
+            .map(|(t, m)| {
+                let cells = [
+                    m.true_positives,
+                    m.false_positives,
+                    m.true_negatives,
+                    m.false_negatives,
+                ];
+                (*t, cells)
+            })
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                (0.3, [2, 1, 1, 0]),
+                (0.5, [1, 0, 2, 1]),
+                (0.0, [2, 2, 0, 0]),
+                // a score equal to the threshold is labelled 0
+                (0.8, [0, 0, 2, 2]),
+            ]
+        );
+        let at = confusion_matrix(&y, &p, 0.3).unwrap();
+        assert_eq!(at.precision(), 2.0 / 3.0);
+        assert_eq!(at.recall(), 1.0);
+        assert_eq!(at.specificity(), 0.5);
+        assert_eq!(at.accuracy(), 0.75);
+        assert!((at.f1() - 0.8).abs() < 1e-12);
+        let none = confusion_matrix(&y, &p, 0.9).unwrap();
+        assert_eq!((none.precision(), none.f1()), (1.0, 0.0));
+    }
+
+    #[test]
+    fn best_threshold_depends_on_the_metric() {
+        let y = DVector::from_vec(vec![0.0, 1.0, 0.0, 1.0, 1.0]);
+        let p = DVector::from_vec(vec![0.2, 0.3, 0.6, 0.7, 0.9]);
+        // F1 is 6/7 at 0.2 against 4/5 at 0.6
+        assert_eq!(best_threshold(&y, &p, MetricKind::F1).unwrap(), 0.2);
+        // J is 2/3 at 0.6 against 1/2 at 0.2
+        assert_eq!(best_threshold(&y, &p, MetricKind::YoudenJ).unwrap(), 0.6);
+        // accuracy ties at 0.8 between 0.2 and 0.6; the lowest wins
+        assert_eq!(best_threshold(&y, &p, MetricKind::Accuracy).unwrap(), 0.2);
+
+        let empty = DVector::from_vec(vec![]);
+        assert!(best_threshold(&empty, &empty, MetricKind::F1).is_err());
+    }
+
+    #[test]
+    fn average_precision_matches_sklearn() {
+        let (y, p) = scored();
+        // sklearn's average_precision_score gives 0.8333...
+        assert!((average_precision(&y, &p).unwrap() - 5.0 / 6.0).abs() < 1e-12);
+        // a single tied score is the base rate
+        let tied = DVector::from_element(4, 0.5);
+        assert_eq!(average_precision(&y, &tied).unwrap(), 0.5);
+        let perfect = DVector::from_vec(vec![0.1, 0.2, 0.8, 0.9]);
+        assert_eq!(average_precision(&y, &perfect).unwrap(), 1.0);
+
+        let err = average_precision(&DVector::zeros(4), &p).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Average precision is undefined without positive outcomes"
+        );
+    }
+
+    #[test]
+    fn metrics_report_gathers_the_scores_and_labels_at_the_threshold() {
+        let (y, p) = scored();
+        let report = metrics_report(&y, &p, 0.5).unwrap();
+        let log_loss = -(0.9f64.ln() + 0.6f64.ln() + 0.35f64.ln() + 0.8f64.ln()) / 4.0;
+        assert_eq!(report.auc, 0.75);
+        assert!((report.average_precision - 5.0 / 6.0).abs() < 1e-12);
+        assert!((report.log_loss - log_loss).abs() < 1e-12);
+        assert!((report.brier_score - 0.158125).abs() < 1e-12);
+        assert_eq!(report.threshold, 0.5);
+        assert_eq!(
+            (report.accuracy, report.precision, report.recall),
+            (0.75, 1.0, 0.5)
+        );
+        assert!((report.f1 - 2.0 / 3.0).abs() < 1e-12);
+
+        let text = report.to_string();
+        assert!(
+            text.starts_with("AUC: 0.7500\naverage precision: 0.8333\n"),
+            "{text}"
+        );
+        assert!(text.ends_with("at threshold 0.5:\n  accuracy: 0.7500\n  precision: 1.0000\n  recall: 0.5000\n  F1: 0.6667"));
+    }
+}
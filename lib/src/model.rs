@@ -0,0 +1,138 @@
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "bincode")]
+use std::fs::File;
+#[cfg(feature = "bincode")]
+use std::io::{BufReader, BufWriter};
+#[cfg(feature = "bincode")]
+use std::path::Path;
+
+use crate::link::Link;
+use crate::models::Findings;
+
+///
+/// The fitted betas without the training data `Findings` borrows; what a
+/// service needs to score new records. Built with `Findings::to_model`.
+///
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Model {
+    /// one per coefficient
+    pub feature_names: Vec<String>,
+    pub coefficients: Vec<f64>,
+    pub intercept: f64,
+    /// the link the model was fitted with
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub link: Link,
+}
+
+impl Findings<'_> {
+    pub fn to_model(&self) -> Model {
+        Model {
+            feature_names: (0..self.coefficients.len())
+                .map(|j| self.objective.feature_name(j))
+                .collect(),
+            coefficients: self.coefficients.iter().copied().collect(),
+            intercept: self.intercept,
+            link: self.cfg.link,
+        }
+    }
+}
+
+impl Model {
+    /// Probability of the positive class for new records; `x` has the layout of
+    /// the objective, intercept placeholder in the last column
+    pub fn predict_proba_on(&self, x: &DMatrix<f64>) -> Result<DVector<f64>> {
+        let p = self.coefficients.len();
+        if x.ncols() != p + 1 {
+            return Err(eyre!(
+                "Expected {} columns (including the intercept), got {}",
+                p + 1,
+                x.ncols()
+            ));
+        }
+        let betas = DVector::from_iterator(
+            p + 1,
+            self.coefficients.iter().copied().chain([self.intercept]),
+        );
+        Ok(self.link.inverse_vec(&(x * betas)))
+    }
+
+    /// Write the model in bincode, far smaller and faster to load than json
+    #[cfg(feature = "bincode")]
+    pub fn save_bincode<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).map_err(|e| eyre!("Failed to create {}: {}", path.display(), e))?;
+        bincode::serialize_into(BufWriter::new(file), self)
+            .map_err(|e| eyre!("Failed to write the model to {}: {}", path.display(), e))
+    }
+
+    /// Read a model written by `save_bincode`
+    #[cfg(feature = "bincode")]
+    pub fn load_bincode<P: AsRef<Path>>(path: P) -> Result<Model> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).map_err(|e| eyre!("Failed to open {}: {}", path.display(), e))?;
+        let model: Model = bincode::deserialize_from(BufReader::new(file))
+            .map_err(|e| eyre!("Failed to read a model from {}: {}", path.display(), e))?;
+        if model.feature_names.len() != model.coefficients.len() {
+            return Err(eyre!(
+                "{} holds {} names for {} coefficients",
+                path.display(),
+                model.feature_names.len(),
+                model.coefficients.len()
+            ));
+        }
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::tests::toy;
+
+    #[test]
+    fn a_model_scores_like_the_findings_it_came_from() {
+        let objective = toy();
+        let findings = objective
+            .fit(CfgBuilder::new().link(Link::Probit).build())
+            .unwrap();
+        let model = findings.to_model();
+        assert_eq!(model.feature_names, ["x0"]);
+        assert_eq!(model.intercept, findings.intercept);
+        assert_eq!(model.link, Link::Probit);
+        let scored = model.predict_proba_on(&objective.x).unwrap();
+        assert!((scored - &findings.predict_proba().inner).amax() < 1e-15);
+
+        let err = model
+            .predict_proba_on(&DMatrix::from_element(1, 3, 1.0))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected 2 columns (including the intercept), got 3"
+        );
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn a_model_round_trips_through_bincode() {
+        let objective = toy();
+        let model = objective.fit(CfgBuilder::new().build()).unwrap().to_model();
+        let path = std::env::temp_dir().join(format!("model-{}.bin", std::process::id()));
+        model.save_bincode(&path).unwrap();
+        assert_eq!(Model::load_bincode(&path).unwrap(), model);
+
+        let mut broken = model.clone();
+        broken.feature_names.push("extra".into());
+        broken.save_bincode(&path).unwrap();
+        let err = Model::load_bincode(&path).unwrap_err().to_string();
+        assert!(err.ends_with("holds 2 names for 1 coefficients"), "{err}");
+        assert!(Model::load_bincode("/no/such/model.bin").is_err());
+    }
+}
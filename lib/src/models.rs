@@ -1,15 +1,92 @@
-use color_eyre::eyre::Result;
+use argmin::core::{Gradient, TerminationReason};
+use color_eyre::eyre::{eyre, Report, Result};
 use nalgebra::base::{DMatrix, DVector, Scalar};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use tracing::{event, Level};
 
 use std::borrow::Borrow;
-use std::cmp::PartialOrd;
 use std::fmt;
-use std::ops::Neg;
+use std::io::Write;
 use std::path::Path;
+use std::str::FromStr;
 
 use crate::auc_score::*;
+use crate::configurations::{Cfg, CfgPredict, CfgReport, SolverKind};
+use crate::inference::validate_level;
+use crate::logit;
 use crate::matrix_csv;
+use crate::metrics::brier_score;
+use crate::preprocessing::is_constant;
+use crate::repro::Fnv1a;
+
+/// `Findings::partial_fit`'s mini-batch when the model wasn't fit with sgd
+const PARTIAL_FIT_BATCH_SIZE: usize = 32;
+
+///
+/// Which input column holds the logit target
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetSpec {
+    /// position among all the input columns
+    Index(usize),
+    /// column name; requires headers
+    Name(String),
+}
+
+impl Default for TargetSpec {
+    fn default() -> Self {
+        TargetSpec::Index(0)
+    }
+}
+
+impl TargetSpec {
+    /// the position of the target among the input `columns`
+    pub(crate) fn resolve(&self, columns: &[String]) -> Result<usize> {
+        match self {
+            TargetSpec::Index(i) if *i < columns.len() => Ok(*i),
+            TargetSpec::Index(i) => Err(eyre!(
+                "Target index {} is out of range for {} columns",
+                i,
+                columns.len()
+            )),
+            TargetSpec::Name(name) => columns
+                .iter()
+                .position(|c| c == name)
+                .ok_or_else(|| eyre!("Target column '{}' not found", name)),
+        }
+    }
+}
+
+///
+/// Where an input matrix holds the intercept placeholder column. An `Objective`
+/// always stores it last (and `Findings::all_betas` ends with the intercept);
+/// `First` inputs are rearranged on the way in.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InterceptPosition {
+    First,
+    #[default]
+    Last,
+}
+
+impl InterceptPosition {
+    /// `x` with its intercept moved last; the moved column must hold only 1s
+    pub(crate) fn to_last(self, x: DMatrix<f64>) -> Result<DMatrix<f64>> {
+        if self == InterceptPosition::Last || x.ncols() == 0 {
+            return Ok(x);
+        }
+        if let Some(i) = x.column(0).iter().position(|v| *v != 1.0) {
+            return Err(eyre!(
+                "The first column is not an intercept: row {} holds {}",
+                i,
+                x[(i, 0)]
+            ));
+        }
+        let ncols = x.ncols();
+        Ok(x.remove_column(0).insert_column(ncols - 1, 1.0))
+    }
+}
 
 ///
 /// specify the objective
@@ -18,6 +95,75 @@ use crate::matrix_csv;
 pub struct Objective {
     pub x: DMatrix<f64>,
     pub y: DVector<f64>,
+    /// predictor names, one per column of x excluding the intercept
+    pub feature_names: Option<Vec<String>>,
+    /// the original target values when y was remapped to 0/1
+    pub label_map: Option<LabelMap>,
+    /// per record weights in the likelihood; `None` weighs every record 1
+    pub weights: Option<DVector<f64>>,
+    /// a per record term added to `X * w` with its coefficient pinned to 1,
+    /// e.g. `log(exposure)`; see `with_offset`
+    pub offset: Option<DVector<f64>>,
+}
+
+///
+/// The two original target values mapped to 0 (the smaller) and 1 (the larger)
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabelMap {
+    pub negative: f64,
+    pub positive: f64,
+}
+
+impl LabelMap {
+    ///
+    /// Detect the two distinct values in `y`; errors when there are more than two
+    ///
+    pub fn detect(y: &DVector<f64>) -> Result<Self> {
+        let mut values: Vec<f64> = Vec::with_capacity(2);
+        for &v in y.iter() {
+            if v.is_nan() {
+                return Err(eyre!("The target holds a NaN"));
+            }
+            if !values.contains(&v) {
+                if values.len() == 2 {
+                    return Err(eyre!(
+                        "The target must hold two distinct values; found {}, {} and {}",
+                        values[0],
+                        values[1],
+                        v
+                    ));
+                }
+                values.push(v);
+            }
+        }
+        match values.as_slice() {
+            [a, b] => Ok(LabelMap {
+                negative: a.min(*b),
+                positive: a.max(*b),
+            }),
+            _ => Err(eyre!(
+                "The target must hold two distinct values; found {:?}",
+                values
+            )),
+        }
+    }
+    /// original value -> 0/1
+    pub fn encode(&self, value: f64) -> f64 {
+        if value == self.positive {
+            1.0
+        } else {
+            0.0
+        }
+    }
+    /// 0/1 -> original value
+    pub fn decode(&self, label: f64) -> f64 {
+        if label == 1.0 {
+            self.positive
+        } else {
+            self.negative
+        }
+    }
 }
 
 impl fmt::Display for Objective {
@@ -36,57 +182,684 @@ impl fmt::Debug for Objective {
         f.debug_struct("Objective")
             .field("x", &self.x.shape())
             .field("y", &self.y.len())
+            .field("feature_names", &self.feature_names)
+            .field("label_map", &self.label_map)
+            .field("weights", &self.weights.as_ref().map(|w| w.len()))
+            .field("offset", &self.offset.as_ref().map(|o| o.len()))
             .finish()
     }
 }
-impl<'a> Objective {
+impl Objective {
     pub fn new(x: DMatrix<f64>, y: DVector<f64>) -> Self {
-        Objective { x, y }
+        Objective {
+            x,
+            y,
+            feature_names: None,
+            label_map: None,
+            weights: None,
+            offset: None,
+        }
+    }
+    ///
+    /// Like `new` for a target coded with any two values (e.g. 1/2); the smaller
+    /// maps to 0, the larger to 1, and the mapping is kept in `label_map`.
+    ///
+    pub fn new_remapped(x: DMatrix<f64>, y: DVector<f64>) -> Result<Self> {
+        let label_map = LabelMap::detect(&y)?;
+        let y = y.map(|v| label_map.encode(v));
+        let mut objective = Objective::new(x, y);
+        objective.label_map = Some(label_map);
+        Ok(objective)
+    }
+    ///
+    /// `from_matrix` without requiring the target to already be coded 0/1
+    ///
+    pub fn from_matrix_remapped(matrix: DMatrix<f64>) -> Result<Self> {
+        let (y, x) = split_target(matrix)?;
+        Objective::new_remapped(x, y)
+    }
+    ///
+    /// Name the predictors (excluding the intercept) for use in reports
+    ///
+    pub fn with_feature_names(mut self, names: Vec<String>) -> Result<Self> {
+        if names.len() != self.predictor_count() {
+            return Err(eyre!(
+                "Expected {} feature names (excluding the intercept), got {}",
+                self.predictor_count(),
+                names.len()
+            ));
+        }
+        self.feature_names = Some(names);
+        Ok(self)
+    }
+    ///
+    /// Add a fixed `offset` to every record's log-odds, in the fit and in the
+    /// predictions on the training records; one finite value per record.
+    ///
+    pub fn with_offset(mut self, offset: DVector<f64>) -> Result<Self> {
+        if offset.len() != self.y.len() {
+            return Err(eyre!(
+                "Got {} offsets for {} records",
+                offset.len(),
+                self.y.len()
+            ));
+        }
+        if let Some(i) = offset.iter().position(|v| !v.is_finite()) {
+            return Err(eyre!(
+                "The offset must be finite; found {} at row {}",
+                offset[i],
+                i
+            ));
+        }
+        self.offset = Some(offset);
+        Ok(self)
+    }
+    /// `X * betas` plus the offset, when there is one
+    pub(crate) fn log_odds(&self, betas: &DVector<f64>) -> DVector<f64> {
+        let z = &self.x * betas;
+        match &self.offset {
+            Some(offset) => z + offset,
+            None => z,
+        }
+    }
+    /// The name of predictor `j`; falls back to `x{j}` when unnamed
+    pub fn feature_name(&self, j: usize) -> String {
+        self.feature_names
+            .as_ref()
+            .and_then(|names| names.get(j).cloned())
+            .unwrap_or_else(|| format!("x{}", j))
     }
     ///
     /// csv -> DMatrix<f64> with placeholder for intercept
     ///
     pub fn from_csv<P: AsRef<Path>>(path: P, with_headers: bool) -> Result<Self> {
         let (staged_records, num_records) = matrix_csv::from_csv(path, with_headers)?;
-        let feature_count = staged_records.len() / num_records;
+        let feature_count = staged_width(staged_records.len(), num_records)?;
+        let dmatrix =
+            DMatrix::from_row_slice(num_records, feature_count, staged_records.as_slice());
+        Objective::try_from(dmatrix)
+    }
+    ///
+    /// `from_csv` with the target in any column; the others, in file order, are
+    /// the predictors. `TargetSpec::Name` requires headers. With headers, a
+    /// target that isn't 0/1 is also logged as a warning naming the column.
+    ///
+    pub fn from_csv_target<P: AsRef<Path>>(
+        path: P,
+        with_headers: bool,
+        target: TargetSpec,
+    ) -> Result<Self> {
+        if let (TargetSpec::Name(name), false) = (&target, with_headers) {
+            return Err(eyre!(
+                "Selecting the target '{}' by name requires headers",
+                name
+            ));
+        }
+        let path = path.as_ref();
+        let columns = matrix_csv::column_names(path, with_headers)?;
+        let target_col = target.resolve(&columns)?;
+
+        let (staged_records, num_records) = matrix_csv::from_csv(path, with_headers)?;
+        let feature_count = staged_width(staged_records.len(), num_records)?;
+        let dmatrix =
+            DMatrix::from_row_slice(num_records, feature_count, staged_records.as_slice());
+        let y = dmatrix.column(target_col).clone_owned();
+        if with_headers && check_binary(&y).is_err() {
+            event!(
+                Level::WARN,
+                "Target column '{}' isn't binary",
+                columns[target_col]
+            );
+        }
+        // target first, as `from_csv` stages it
+        let mut dmatrix = dmatrix.remove_column(target_col).insert_column(0, 0.0);
+        dmatrix.set_column(0, &y);
+        Objective::try_from(dmatrix)
+    }
+    ///
+    /// `from_csv` for pre-aggregated records: the column named `frequency` counts
+    /// how many identical records each row stands for and becomes the weights,
+    /// not a predictor, so the fit matches that of the expanded file. The counts
+    /// must be positive.
+    ///
+    pub fn from_csv_weighted<P: AsRef<Path>>(
+        path: P,
+        with_headers: bool,
+        frequency: &str,
+    ) -> Result<Self> {
+        if !with_headers {
+            return Err(eyre!(
+                "Selecting the frequency column '{}' by name requires headers",
+                frequency
+            ));
+        }
+        let path = path.as_ref();
+        let columns = matrix_csv::column_names(path, with_headers)?;
+        let j = match columns.iter().position(|c| c == frequency) {
+            Some(0) => return Err(eyre!("The target can't also be the frequency column")),
+            Some(j) => j,
+            None => return Err(eyre!("Frequency column '{}' not found", frequency)),
+        };
+
+        let (staged_records, num_records) = matrix_csv::from_csv(path, with_headers)?;
+        let feature_count = staged_width(staged_records.len(), num_records)?;
         let dmatrix =
             DMatrix::from_row_slice(num_records, feature_count, staged_records.as_slice());
-        Ok(dmatrix.into())
+        let counts: DVector<f64> = dmatrix.column(j).into();
+        if let Some(i) = counts.iter().position(|c| !(c.is_finite() && *c > 0.0)) {
+            return Err(eyre!(
+                "Frequencies must be positive; found {} in record {}",
+                counts[i],
+                i
+            ));
+        }
+        let mut objective = Objective::try_from(dmatrix.remove_column(j))?;
+        objective.weights = Some(counts);
+        Ok(objective)
+    }
+    ///
+    /// `from_csv` with the string valued input `column` feature hashed into
+    /// `width` 0/1 predictors, in its place; see `matrix_csv::from_csv_hashed`.
+    /// Values that share a bucket share a coefficient. The buckets are named
+    /// `name#b` (a column without a header is `x{j}`), so the objective always
+    /// carries feature names.
+    ///
+    pub fn from_csv_hashed<P: AsRef<Path>>(
+        path: P,
+        with_headers: bool,
+        column: usize,
+        width: usize,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let dmatrix = matrix_csv::from_csv_hashed(path, with_headers, column, width)?;
+        let columns = matrix_csv::column_names(path, with_headers)?;
+        let name = |j: usize| match with_headers {
+            true => columns[j].clone(),
+            false => format!("x{}", j - 1),
+        };
+        let names = (1..columns.len())
+            .flat_map(|j| match j == column {
+                true => (0..width).map(|b| format!("{}#{}", name(j), b)).collect(),
+                false => vec![name(j)],
+            })
+            .collect();
+        Objective::try_from(dmatrix)?.with_feature_names(names)
+    }
+    ///
+    /// csv read at precision `N` (e.g. `f32`, halving the memory used to stage the
+    /// records) then widened to the `f64` the solver always runs at.
+    ///
+    /// `Objective::from_csv_as::<f32, _>(path, true)`
+    ///
+    pub fn from_csv_as<N, P>(path: P, with_headers: bool) -> Result<Self>
+    where
+        N: FromStr + Scalar + Into<f64>,
+        <N as FromStr>::Err: std::error::Error + Send + Sync,
+        P: AsRef<Path>,
+    {
+        let (staged_records, num_records) = matrix_csv::from_csv::<P, N>(path, with_headers)?;
+        let feature_count = staged_width(staged_records.len(), num_records)?;
+        let dmatrix = DMatrix::from_row_iterator(
+            num_records,
+            feature_count,
+            staged_records.into_iter().map(Into::into),
+        );
+        Objective::try_from(dmatrix)
+    }
+    ///
+    /// Memory bounded alternative to `from_csv`; see `matrix_csv::from_csv_streaming`
+    ///
+    pub fn from_csv_streaming<P: AsRef<Path>>(path: P, with_headers: bool) -> Result<Self> {
+        Objective::try_from(matrix_csv::from_csv_streaming(path, with_headers)?)
+    }
+    ///
+    /// x from one csv (every column a predictor; the intercept is appended) and
+    /// the target from another holding a single column, row for row.
+    ///
+    pub fn from_csv_xy<P: AsRef<Path>>(x_path: P, y_path: P, with_headers: bool) -> Result<Self> {
+        let (x_records, x_rows) = matrix_csv::from_csv(x_path, with_headers)?;
+        let (y_records, y_rows) = matrix_csv::from_csv::<_, f64>(y_path, with_headers)?;
+        if x_rows != y_rows {
+            return Err(eyre!(
+                "The x file has {} records but the y file has {}",
+                x_rows,
+                y_rows
+            ));
+        }
+        if x_rows == 0 {
+            return Err(eyre!("The x file has no records"));
+        }
+        // each staged y record is the target then the intercept placeholder
+        if y_records.len() / y_rows != 2 {
+            return Err(eyre!(
+                "The y file must hold a single column, got {}",
+                y_records.len() / y_rows - 1
+            ));
+        }
+
+        let x = DMatrix::from_row_slice(x_rows, x_records.len() / x_rows, &x_records);
+        let y: DVector<f64> = y_records
+            .into_iter()
+            .step_by(2)
+            .collect::<Vec<f64>>()
+            .into();
+        check_binary(&y)?;
+        check_finite(&x)?;
+        Ok(Objective::new(x, y))
     }
     /// The data has target in the first slot, and bias/intercept in the last slot
     pub fn from_vec(data: Vec<f64>, rows: usize) -> Result<Self> {
-        // assert rows make sense given length
-        let cols = data.len() / rows;
+        let cols = check_rows("data", data.len(), rows)?;
         let dmatrix = DMatrix::from_row_slice(rows, cols, &data);
-        Ok(dmatrix.into())
+        Objective::try_from(dmatrix)
+    }
+    ///
+    /// One `Vec` per record holding the target at `target_col` among the
+    /// predictors (no intercept; it is appended). Every row must be as long.
+    ///
+    pub fn from_rows(rows: Vec<Vec<f64>>, target_col: usize) -> Result<Self> {
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 {
+            return Err(eyre!("The rows hold no values"));
+        }
+        if let Some(i) = rows.iter().position(|row| row.len() != width) {
+            return Err(eyre!(
+                "Row {} holds {} values; the first row holds {}",
+                i,
+                rows[i].len(),
+                width
+            ));
+        }
+        if target_col >= width {
+            return Err(eyre!(
+                "Target column {} is out of range for rows of {} values",
+                target_col,
+                width
+            ));
+        }
+        // target first, the predictors in order, then the intercept placeholder
+        let dmatrix = DMatrix::from_fn(rows.len(), width + 1, |i, j| match j {
+            0 => rows[i][target_col],
+            j if j == width => 1.0,
+            j if j <= target_col => rows[i][j - 1],
+            j => rows[i][j],
+        });
+        Objective::try_from(dmatrix)
     }
     ///
     /// Build using X separate from Y
     ///
     pub fn from_vecs(x: Vec<f64>, y: Vec<f64>, rows: usize) -> Result<Self> {
-        // assert rows make sense given length
-        let x_cols = x.len() / rows;
+        let x_cols = check_rows("x", x.len(), rows)?;
+        if y.len() != rows {
+            return Err(eyre!("Expected {} target values, got {}", rows, y.len()));
+        }
         let x_dmatrix = DMatrix::from_row_slice(rows, x_cols, &x);
-        Ok(Objective::new(x_dmatrix, y.into()))
+        let y = DVector::from_vec(y);
+        check_binary(&y)?;
+        check_finite(&x_dmatrix)?;
+        Ok(Objective::new(x_dmatrix, y))
     }
     /// alias that points to trait
     pub fn from_matrix(matrix: DMatrix<f64>) -> Result<Self> {
-        Ok(matrix.into())
+        Objective::try_from(matrix)
     }
+    ///
+    /// `from_matrix` for a matrix (target still first) whose intercept column
+    /// sits at `position` among the columns of x
+    ///
+    pub fn from_matrix_with(matrix: DMatrix<f64>, position: InterceptPosition) -> Result<Self> {
+        let (y, x) = split_target(matrix)?;
+        check_binary(&y)?;
+        let x = position.to_last(x)?;
+        check_finite(&x)?;
+        Ok(Objective::new(x, y))
+    }
+    ///
+    /// Columns of x *including* the intercept placeholder; the length of the betas
+    /// the solver fits. See `predictor_count` for the actual predictors.
+    ///
     pub fn feature_count(&self) -> usize {
         self.x.shape().1
     }
+    /// Predictors in x, excluding the intercept; one per coefficient and name
+    pub fn predictor_count(&self) -> usize {
+        self.feature_count() - 1
+    }
+    /// The number of records
+    pub fn nrows(&self) -> usize {
+        self.x.nrows()
+    }
+    /// The 0/1 target
+    pub fn target(&self) -> &DVector<f64> {
+        &self.y
+    }
+    /// A copy of predictor `j`; the intercept isn't a feature
+    pub fn feature_column(&self, j: usize) -> Result<DVector<f64>> {
+        if j >= self.predictor_count() {
+            return Err(eyre!(
+                "Feature {} is out of range for {} predictors",
+                j,
+                self.predictor_count()
+            ));
+        }
+        Ok(self.x.column(j).into_owned())
+    }
+    /// A copy of the predictors of record `i`, the input `Findings::predict_row` takes
+    pub fn row(&self, i: usize) -> Result<DVector<f64>> {
+        if i >= self.nrows() {
+            return Err(eyre!(
+                "Row {} is out of range for {} records",
+                i,
+                self.nrows()
+            ));
+        }
+        Ok(self.x.row(i).columns(0, self.predictor_count()).transpose())
+    }
+    /// Share of records labelled 1, the mean of y (unweighted)
+    pub fn positive_rate(&self) -> f64 {
+        self.y.mean()
+    }
+    /// `(negatives, positives)`
+    pub fn class_counts(&self) -> (usize, usize) {
+        let positives = self.y.iter().filter(|&&v| v == 1.0).count();
+        (self.y.len() - positives, positives)
+    }
+    ///
+    /// Ratio of the largest to the smallest singular value of x (the intercept
+    /// included, so a constant predictor counts as collinear); from the
+    /// eigenvalues of `X' X`. Infinite when a column is a linear combination of
+    /// the others.
+    ///
+    pub fn condition_number(&self) -> f64 {
+        let eigenvalues = self.x.tr_mul(&self.x).symmetric_eigenvalues();
+        let largest = eigenvalues.max();
+        let smallest = eigenvalues.min();
+        if smallest <= largest * f64::EPSILON {
+            f64::INFINITY
+        } else {
+            (largest / smallest).sqrt()
+        }
+    }
+    ///
+    /// `(name, vif)` of each predictor: `1 / (1 - R²)` of its regression on the
+    /// other predictors, read off the inverse of their correlation matrix. 1 is
+    /// uncorrelated; above 10 is the usual sign of a collinear predictor.
+    ///
+    pub fn vif(&self) -> Result<Vec<(String, f64)>> {
+        let p = self.predictor_count();
+        let stats = self.column_stats();
+        if let Some(j) = (0..p).find(|&j| is_constant(self.x.column(j).iter())) {
+            return Err(eyre!(
+                "Predictor '{}' is constant, so it has no vif",
+                self.feature_name(j)
+            ));
+        }
+        let n = self.x.nrows() as f64;
+        let z = DMatrix::from_fn(self.x.nrows(), p, |i, j| {
+            (self.x[(i, j)] - stats[j].0) / stats[j].1
+        });
+        let corr = z.tr_mul(&z) / n;
+        let corr_inv = corr.try_inverse().ok_or_else(|| {
+            eyre!("The predictors' correlation matrix is singular; a predictor is an exact combination of others")
+        })?;
+
+        Ok((0..p)
+            .map(|j| (self.feature_name(j), corr_inv[(j, j)]))
+            .collect())
+    }
+    /// The weight of record `i`; 1 when unweighted
+    pub(crate) fn weight(&self, i: usize) -> f64 {
+        self.weights.as_ref().map_or(1.0, |w| w[i])
+    }
+    /// The (weighted) share of positive records
+    pub(crate) fn base_rate(&self) -> f64 {
+        match &self.weights {
+            None => self.y.mean(),
+            Some(w) => w.dot(&self.y) / w.sum(),
+        }
+    }
+    ///
+    /// Fit the logit model; the primary entry point. `logit::run` remains the
+    /// lower-level api, generic over the `Design`.
+    ///
+    pub fn fit(&self, cfg: Cfg) -> Result<Findings<'_>> {
+        logit::run(self, cfg)
+    }
+    /// `fit` then `predict_proba` on the training records, dropping the `Findings`
+    pub fn fit_predict(&self, cfg: Cfg) -> Result<Prediction<f64>> {
+        Ok(self.fit(cfg)?.predict_proba())
+    }
+    ///
+    /// The intercept-only model in closed form, without running the solver: the
+    /// coefficients are zero and the intercept is `logit(base rate) = ln(pos / neg)`,
+    /// with the positives and negatives summed by weight when the records carry
+    /// them. That is only the maximum likelihood fit without an offset. Errors
+    /// when every record is in one class, where the intercept is infinite.
+    ///
+    pub fn fit_null(&self) -> Result<Findings<'_>> {
+        let p = self.feature_count();
+        let base_rate = self.base_rate();
+        if !(base_rate > 0.0 && base_rate < 1.0) {
+            return Err(eyre!(
+                "The null model needs both classes; the base rate is {}",
+                base_rate
+            ));
+        }
+        let mut all_betas = DVector::zeros(p);
+        all_betas[p - 1] = (base_rate / (1.0 - base_rate)).ln();
+
+        Ok(Findings {
+            coefficients: all_betas.rows(0, p - 1).into_owned(),
+            intercept: all_betas[p - 1],
+            final_nll: logit::neg_log_likelihood(
+                &self.log_odds(&all_betas),
+                &self.y,
+                self.weights.as_ref(),
+            ),
+            gradient_norm: self.gradient(&all_betas).map_or(f64::NAN, |g| g.norm()),
+            all_betas,
+            objective: self,
+            cfg: Cfg::default(),
+            cost_history: Vec::new(),
+            default_prediction: None,
+            separation_warning: false,
+            collinearity_warning: false,
+            timed_out: false,
+            iterations: 0,
+            termination: TerminationReason::SolverConverged,
+        })
+    }
+}
+
+///
+/// The number of columns in the `len` values `matrix_csv` staged for
+/// `num_records` records; a file without records has none to divide by
+///
+fn staged_width(len: usize, num_records: usize) -> Result<usize> {
+    if num_records == 0 {
+        return Err(eyre!("The csv holds no records"));
+    }
+    Ok(len / num_records)
+}
+
+/// Rows with the target first; see `Objective::from_rows`
+impl std::convert::TryFrom<Vec<Vec<f64>>> for Objective {
+    type Error = Report;
+    fn try_from(rows: Vec<Vec<f64>>) -> Result<Self> {
+        Objective::from_rows(rows, 0)
+    }
 }
 
 /// The logit target must be in the first column of the matrix.
-impl std::convert::From<DMatrix<f64>> for Objective {
-    fn from(matrix: DMatrix<f64>) -> Self {
-        let (_, w) = matrix.shape();
-        let y: DVector<f64> = matrix.column(0).into();
-        let x: DMatrix<f64> = matrix.columns(1, w - 1).into();
-        // todo: make this assertion more in-line with binary the assertion
-        assert!(y.min() == 0.0 && y.max() == 1.0);
-        Objective { x, y }
+impl std::convert::TryFrom<DMatrix<f64>> for Objective {
+    type Error = Report;
+    fn try_from(matrix: DMatrix<f64>) -> Result<Self> {
+        let (y, x) = split_target(matrix)?;
+        check_binary(&y)?;
+        check_finite(&x)?;
+        Ok(Objective::new(x, y))
+    }
+}
+
+///
+/// Assembles an `Objective` and validates it once, in `build`: x and y are set
+/// and agree on the record count, x includes the intercept and holds finite
+/// values, y is 0/1, and the optional names and weights fit the design.
+///
+/// `ObjectiveBuilder::new().x(x).y(y).weights(w).build()?`
+///
+#[derive(Debug, Default)]
+pub struct ObjectiveBuilder {
+    x: Option<DMatrix<f64>>,
+    y: Option<DVector<f64>>,
+    feature_names: Option<Vec<String>>,
+    weights: Option<DVector<f64>>,
+    offset: Option<DVector<f64>>,
+    intercept: InterceptPosition,
+}
+
+impl ObjectiveBuilder {
+    pub fn new() -> ObjectiveBuilder {
+        ObjectiveBuilder::default()
+    }
+    /// the design, intercept placeholder in the last column unless `intercept` says otherwise
+    pub fn x(mut self, x: DMatrix<f64>) -> Self {
+        self.x = Some(x);
+        self
+    }
+    /// the 0/1 target
+    pub fn y(mut self, y: DVector<f64>) -> Self {
+        self.y = Some(y);
+        self
+    }
+    /// one per predictor, excluding the intercept
+    pub fn feature_names(mut self, names: Vec<String>) -> Self {
+        self.feature_names = Some(names);
+        self
+    }
+    /// one finite, non-negative weight per record
+    pub fn weights(mut self, weights: DVector<f64>) -> Self {
+        self.weights = Some(weights);
+        self
+    }
+    /// one finite value per record added to the log-odds; see `Objective::with_offset`
+    pub fn offset(mut self, offset: DVector<f64>) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+    /// where x holds the intercept column; `Last` by default
+    pub fn intercept(mut self, position: InterceptPosition) -> Self {
+        self.intercept = position;
+        self
+    }
+    pub fn build(self) -> Result<Objective> {
+        let x = self.x.ok_or_else(|| eyre!("The builder is missing x"))?;
+        let y = self.y.ok_or_else(|| eyre!("The builder is missing y"))?;
+        if x.nrows() != y.len() {
+            return Err(eyre!(
+                "x has {} rows but y has {} values",
+                x.nrows(),
+                y.len()
+            ));
+        }
+        if x.ncols() == 0 {
+            return Err(eyre!("x needs at least the intercept column"));
+        }
+        let x = self.intercept.to_last(x)?;
+        check_finite(&x)?;
+        check_binary(&y)?;
+
+        let mut objective = Objective::new(x, y);
+        if let Some(names) = self.feature_names {
+            objective = objective.with_feature_names(names)?;
+        }
+        if let Some(weights) = self.weights {
+            if weights.len() != objective.y.len() {
+                return Err(eyre!(
+                    "Got {} weights for {} records",
+                    weights.len(),
+                    objective.y.len()
+                ));
+            }
+            if let Some(i) = weights.iter().position(|w| !w.is_finite() || *w < 0.0) {
+                return Err(eyre!(
+                    "Weights must be finite and non-negative; found {} at row {}",
+                    weights[i],
+                    i
+                ));
+            }
+            objective.weights = Some(weights);
+        }
+        if let Some(offset) = self.offset {
+            objective = objective.with_offset(offset)?;
+        }
+        Ok(objective)
+    }
+}
+
+/// target in the first column -> (y, x)
+fn split_target(matrix: DMatrix<f64>) -> Result<(DVector<f64>, DMatrix<f64>)> {
+    let (_, w) = matrix.shape();
+    if w < 2 {
+        return Err(eyre!(
+            "Expected the target and at least the intercept column, got {} columns",
+            w
+        ));
+    }
+    let y: DVector<f64> = matrix.column(0).into();
+    let x: DMatrix<f64> = matrix.columns(1, w - 1).into();
+    Ok((y, x))
+}
+
+///
+/// The number of columns when the `len` values of `what` are laid out in `rows`
+/// rows; a remainder would otherwise shift every row after the first
+///
+fn check_rows(what: &str, len: usize, rows: usize) -> Result<usize> {
+    if rows == 0 {
+        return Err(eyre!("{} can't be laid out in 0 rows", what));
+    }
+    if !len.is_multiple_of(rows) {
+        return Err(eyre!(
+            "The {} values of {} do not fill {} rows evenly ({} left over)",
+            len,
+            what,
+            rows,
+            len % rows
+        ));
+    }
+    Ok(len / rows)
+}
+
+///
+/// NaN or infinite predictors (e.g. an upstream division by zero) would only
+/// surface as NaN coefficients; name the first one instead. Rows and columns
+/// index x, so the target column is not counted.
+///
+pub(crate) fn check_finite(x: &DMatrix<f64>) -> Result<()> {
+    for (i, row) in x.row_iter().enumerate() {
+        if let Some(j) = row.iter().position(|v| !v.is_finite()) {
+            return Err(eyre!(
+                "x holds {} at row {}, column {}; drop or impute non-finite values first",
+                row[j],
+                i,
+                j
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The logit target must only hold 0 and 1
+pub(crate) fn check_binary(y: &DVector<f64>) -> Result<()> {
+    match y.iter().position(|&v| v != 0.0 && v != 1.0) {
+        None => Ok(()),
+        Some(i) => Err(eyre!(
+            "The target must be binary (0 or 1); found {} at row {} (see `Objective::new_remapped`)",
+            y[i],
+            i
+        )),
     }
 }
 
@@ -99,32 +872,200 @@ impl std::convert::From<DMatrix<f64>> for Objective {
 ///
 /// Lifetime is tied to objective
 ///
-pub struct Findings<'a> {
+pub struct Findings<'a, O = Objective> {
     pub all_betas: DVector<f64>,
     pub coefficients: DVector<f64>,
     pub intercept: f64,
-    pub objective: &'a Objective,
+    pub objective: &'a O,
+    /// the configuration used to derive the findings
+    pub cfg: Cfg,
+    /// the negative log-likelihood at the fitted betas
+    pub final_nll: f64,
+    /// the norm of the cost's gradient at the fitted betas; near 0 at the optimum
+    pub gradient_norm: f64,
+    /// the cost after each iteration of the solver
+    pub cost_history: Vec<f64>,
+    /// the prediction requested with `CfgBuilder::with_predict`
+    pub default_prediction: Option<Prediction<f64>>,
+    /// the classes look perfectly separated; the betas diverged rather than converged
+    pub separation_warning: bool,
+    /// the condition number of x is so large that the betas and their standard
+    /// errors are unstable; a predictor is (nearly) a combination of others
+    pub collinearity_warning: bool,
+    /// the fit hit `Cfg::max_duration`; the betas are the best found so far
+    pub timed_out: bool,
+    /// how many iterations (epochs for sgd) the solver ran
+    pub iterations: u64,
+    /// why the solver stopped, e.g. `MaxItersReached` suggests retrying with more
+    pub termination: TerminationReason,
+}
+impl fmt::Display for Findings<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Findings with {} features, intercept {} and AUC ",
+            self.objective.predictor_count(),
+            self.intercept
+        )?;
+        match auc_score(&self.objective.y, &self.predict_proba()) {
+            Ok(auc) => write!(f, "{}", auc),
+            Err(_) => f.write_str("n/a"),
+        }
+    }
 }
 impl<'a> Findings<'a> {
+    ///
+    /// Online update: `passes` epochs of sgd over a new `batch` starting from
+    /// these betas, so a model follows a stream without refitting from scratch.
+    /// Chain the calls to carry the state forward. The batch size and momentum
+    /// come from the cfg when it is already sgd; its other settings (l2, seed)
+    /// carry over.
+    ///
+    pub fn partial_fit(
+        self,
+        batch: &Objective,
+        learning_rate: f64,
+        passes: usize,
+    ) -> Result<Findings<'_>> {
+        if batch.feature_count() != self.all_betas.len() {
+            return Err(eyre!(
+                "The batch has {} columns (intercept included) but the model {}",
+                batch.feature_count(),
+                self.all_betas.len()
+            ));
+        }
+        let (batch_size, momentum) = match self.cfg.solver {
+            SolverKind::Sgd {
+                batch_size,
+                momentum,
+                ..
+            } => (batch_size, momentum),
+            _ => (PARTIAL_FIT_BATCH_SIZE, 0.0),
+        };
+        let mut cfg = self.cfg;
+        cfg.solver = SolverKind::Sgd {
+            batch_size,
+            learning_rate,
+            epochs: passes as u64,
+            momentum,
+        };
+        cfg.warm_start = Some(self.all_betas);
+        logit::run(batch, cfg)
+    }
     pub fn report(&self) -> Result<String> {
-        let report = format!(
+        self.report_with(&CfgReport::default())
+    }
+    pub fn report_with(&self, cfg: &CfgReport) -> Result<String> {
+        let mut buffer = Vec::new();
+        self.write_report_with(&mut buffer, cfg)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+    /// Stream the report to a file, stdout, a buffer...
+    pub fn write_report<W: Write>(&self, w: &mut W) -> Result<()> {
+        self.write_report_with(w, &CfgReport::default())
+    }
+    pub fn write_report_with<W: Write>(&self, w: &mut W, cfg: &CfgReport) -> Result<()> {
+        if let Some(level) = cfg.confidence_level {
+            validate_level(level)?;
+        }
+        // inference is left out when the information matrix can't be inverted
+        let z = self.z_statistics().ok();
+        let ci = cfg
+            .confidence_level
+            .and_then(|level| Some((level, self.confidence_intervals(level).ok()?)));
+        // the intercept's exp is the baseline odds, the coefficients' an odds ratio
+        let details_of = |j: usize, odds: &str| {
+            let mut parts = vec![format!("{}: {}", odds, self.all_betas[j].exp())];
+            if let Some(z) = &z {
+                parts.push(format!("z: {:.3}", z[j]));
+            }
+            if let Some((level, ci)) = &ci {
+                parts.push(format!(
+                    "{}% CI: [{:.4}, {:.4}]",
+                    level * 100.0,
+                    ci[j].0,
+                    ci[j].1
+                ));
+            }
+            format!(" ({})", parts.join(", "))
+        };
+        let coefficients: String = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .map(|(j, beta)| {
+                format!(
+                    "\n  {}: {}{}",
+                    self.objective.feature_name(j),
+                    beta,
+                    details_of(j, "odds ratio")
+                )
+            })
+            .collect();
+        let marginal_effects: String = if cfg.marginal_effects {
+            let effects = self.average_marginal_effects();
+            let lines: String = effects
+                .iter()
+                .enumerate()
+                .map(|(j, effect)| format!("\n  {}: {}", self.objective.feature_name(j), effect))
+                .collect();
+            format!("\naverage marginal effects:{}", lines)
+        } else {
+            String::new()
+        };
+
+        let (negatives, positives) = self.objective.class_counts();
+
+        write!(
+            w,
             r#"
 -----------------------------------
 features: {}
 records: {}
-coefficients: {}
-intercept: {}
+positives: {}
+negatives: {}
+base rate: {}
+coefficients:{}
+intercept: {}{}{}
+AIC: {}
+BIC: {}
 AUC score: {}
+Brier score: {}
 -----------------------------------
 "#,
-            self.objective.feature_count(),
+            self.objective.predictor_count(),
             self.objective.x.shape().0,
-            self.coefficients,
+            positives,
+            negatives,
+            self.objective.base_rate(),
+            coefficients,
             self.intercept,
-            auc_score(&self.objective.y, &self.predict(true))?,
-        );
+            details_of(self.coefficients.len(), "odds"),
+            marginal_effects,
+            self.aic(),
+            self.bic(),
+            auc_score(&self.objective.y, &self.predict_proba())?,
+            brier_score(&self.objective.y, &self.predict_proba())?,
+        )?;
 
-        Ok(report)
+        Ok(())
+    }
+    /// The headline numbers of the report, for other tools to consume
+    pub fn summary(&self) -> Result<FindingsSummary> {
+        Ok(FindingsSummary {
+            feature_names: (0..self.coefficients.len())
+                .map(|j| self.objective.feature_name(j))
+                .collect(),
+            coefficients: self.coefficients.iter().copied().collect(),
+            intercept: self.intercept,
+            auc: auc_score(&self.objective.y, &self.predict_proba())?,
+        })
+    }
+    /// `summary` as a json object
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self.summary()?)
+            .map_err(|e| eyre!("Failed to write the findings as json: {}", e))
     }
     /*
     pub fn coefficients(&self) -> &DVector<f64> {
@@ -143,27 +1084,195 @@ AUC score: {}
         event!(Level::DEBUG, "🦀 row width: {}", &x.shape().1);
         event!(Level::DEBUG, "🦀 -----------------------------------");
 
-        let mut y_hat: DVector<f64> = x * coeff;
+        let link = self.cfg.link;
+        let mut y_hat: DVector<f64> = self.objective.log_odds(coeff);
         if binary {
             y_hat.apply(|v| {
-                if sigmoid(*v) > 0.5 {
+                if link.inverse(*v) > 0.5 {
                     *v = 1.0;
                 } else {
                     *v = 0.0;
                 }
             });
         } else {
-            y_hat.apply(|v| {
-                *v = f64::exp(*v) / (f64::exp(*v) + 1.0);
-            });
+            y_hat.apply(|v| *v = link.inverse(*v));
         }
 
         Prediction::new(y_hat)
     }
+    ///
+    /// The linear predictor `X * w` (log-odds), without the sigmoid; enough when
+    /// only the ranking matters or to feed a downstream calibrator.
+    ///
+    pub fn decision_function(&self) -> Prediction<f64> {
+        Prediction::new(self.objective.log_odds(&self.all_betas))
+    }
+    ///
+    /// `decision_function` for new records; `x` has the layout of the objective,
+    /// intercept placeholder in the last column. New records carry no offset.
+    ///
+    pub fn decision_function_on(&self, x: &DMatrix<f64>) -> Result<Prediction<f64>> {
+        if x.ncols() != self.all_betas.len() {
+            return Err(eyre!(
+                "Expected {} columns (including the intercept), got {}",
+                self.all_betas.len(),
+                x.ncols()
+            ));
+        }
+        Ok(Prediction::new(x * &self.all_betas))
+    }
+    /// Probability of the positive class for each record
+    pub fn predict_proba(&self) -> Prediction<f64> {
+        Prediction::new(self.cfg.link.inverse_vec(&self.decision_function().inner))
+    }
+    /// `predict_proba` for new records; see `decision_function_on`
+    pub fn predict_proba_on(&self, x: &DMatrix<f64>) -> Result<Prediction<f64>> {
+        Ok(Prediction::new(
+            self.cfg
+                .link
+                .inverse_vec(&self.decision_function_on(x)?.inner),
+        ))
+    }
+    ///
+    /// `predict_proba_on` `batch_rows` records at a time, so only one batch's
+    /// log-odds is held next to the output; the probabilities are the same.
+    ///
+    pub fn predict_proba_batched(
+        &self,
+        x: &DMatrix<f64>,
+        batch_rows: usize,
+    ) -> Result<Prediction<f64>> {
+        if batch_rows == 0 {
+            return Err(eyre!("The batch needs at least 1 row"));
+        }
+        if x.ncols() != self.all_betas.len() {
+            return Err(eyre!(
+                "Expected {} columns (including the intercept), got {}",
+                self.all_betas.len(),
+                x.ncols()
+            ));
+        }
+        let mut probabilities = DVector::zeros(x.nrows());
+        for start in (0..x.nrows()).step_by(batch_rows) {
+            let rows = batch_rows.min(x.nrows() - start);
+            let log_odds = x.rows(start, rows) * &self.all_betas;
+            probabilities
+                .rows_mut(start, rows)
+                .copy_from(&self.cfg.link.inverse_vec(&log_odds));
+        }
+        Ok(Prediction::new(probabilities))
+    }
+    ///
+    /// Score a single record of predictors (no intercept) without building a
+    /// matrix: its probability, or with `binary` its 0/1 label at `threshold`
+    ///
+    pub fn predict_row(&self, features: &[f64], binary: bool, threshold: f64) -> Result<f64> {
+        if features.len() != self.coefficients.len() {
+            return Err(eyre!(
+                "Expected {} predictors, got {}",
+                self.coefficients.len(),
+                features.len()
+            ));
+        }
+        let z: f64 = features
+            .iter()
+            .zip(self.coefficients.iter())
+            .map(|(v, b)| v * b)
+            .sum();
+        let probability = self.cfg.link.inverse(z + self.intercept);
+        match binary {
+            true if probability > threshold => Ok(1.0),
+            true => Ok(0.0),
+            false => Ok(probability),
+        }
+    }
+    ///
+    /// `predict_proba_on` for a csv too large to load: each record holds the
+    /// predictors only (no target, no intercept) and its probability is written
+    /// to `out_path`, one per line under a `probability` header when the input
+    /// has headers. Memory stays constant in the number of records.
+    ///
+    pub fn predict_csv<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        path: P,
+        with_headers: bool,
+        out_path: Q,
+    ) -> Result<()> {
+        let header: &[&str] = if with_headers { &["probability"] } else { &[] };
+        let coefficients = self.coefficients.as_slice();
+        matrix_csv::map_records(
+            path,
+            with_headers,
+            out_path,
+            coefficients.len(),
+            header,
+            |record| {
+                let z: f64 = record.iter().zip(coefficients).map(|(v, b)| v * b).sum();
+                self.cfg.link.inverse(z + self.intercept)
+            },
+        )
+    }
+    ///
+    /// Probabilities and the 0/1 labels at `threshold` from a single `X * w`
+    ///
+    pub fn predict_both(&self, threshold: f64) -> (Prediction<f64>, Prediction<f64>) {
+        let probabilities = Prediction::new(
+            self.cfg
+                .link
+                .inverse_vec(&self.objective.log_odds(&self.all_betas)),
+        );
+        let labels = probabilities.threshold(threshold);
+
+        (probabilities, labels)
+    }
+    ///
+    /// Labels at 0.5 reported in the target's original coding; the same as
+    /// `predict(true)` when the objective was not remapped.
+    ///
+    pub fn predict_original(&self) -> Prediction<f64> {
+        let labels = self.predict(true);
+        match &self.objective.label_map {
+            Some(label_map) => Prediction::new(labels.inner.map(|v| label_map.decode(v))),
+            None => labels,
+        }
+    }
 }
 
+///
+/// The coefficients, named (`x{j}` when unnamed), with the training AUC
+///
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FindingsSummary {
+    pub feature_names: Vec<String>,
+    pub coefficients: Vec<f64>,
+    pub intercept: f64,
+    pub auc: f64,
+}
+
+#[derive(Debug)]
 pub struct Prediction<T> {
-    inner: DVector<T>,
+    pub(crate) inner: DVector<T>,
+}
+impl<T> fmt::Display for Prediction<T>
+where
+    T: Scalar + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const SHOWN: usize = 5;
+        let head: Vec<String> = self.inner.iter().take(SHOWN).map(T::to_string).collect();
+        write!(
+            f,
+            "Prediction of length {}: [{}{}]",
+            self.inner.len(),
+            head.join(", "),
+            if self.inner.len() > SHOWN {
+                ", ..."
+            } else {
+                ""
+            }
+        )
+    }
 }
 impl<T> std::ops::Deref for Prediction<T> {
     type Target = DVector<T>;
@@ -189,38 +1298,1121 @@ where
         prediction.inner.as_slice().to_vec()
     }
 }
+impl Prediction<f64> {
+    /// probabilities, or their labels when `cfg.binary_output`
+    pub(crate) fn from_cfg(probabilities: DVector<f64>, cfg: &CfgPredict) -> Self {
+        let probabilities = Prediction::new(probabilities);
+        if cfg.binary_output {
+            probabilities.threshold(cfg.threshold)
+        } else {
+            probabilities
+        }
+    }
+    /// 0/1 labels from probabilities: 1 above `threshold`
+    pub fn threshold(&self, threshold: f64) -> Prediction<f64> {
+        Prediction::new(self.inner.map(|p| if p > threshold { 1.0 } else { 0.0 }))
+    }
+    /// One prediction per line, optionally under a header
+    pub fn to_csv<P: AsRef<Path>>(&self, path: P, header: Option<&str>) -> Result<()> {
+        matrix_csv::write_column(path, self.inner.as_slice(), None, header.as_slice())
+    }
+    /// Two columns, the id then the prediction; `header` names both
+    pub fn to_csv_with_ids<P: AsRef<Path>>(
+        &self,
+        path: P,
+        ids: &[String],
+        header: Option<(&str, &str)>,
+    ) -> Result<()> {
+        let header = header
+            .map(|(id, value)| vec![id, value])
+            .unwrap_or_default();
+        matrix_csv::write_column(path, self.inner.as_slice(), Some(ids), &header)
+    }
+}
+impl Prediction<usize> {
+    ///
+    /// The most probable class of each row of a `records x classes` probability
+    /// matrix, e.g. `MultinomialFindings::predict_proba`; the first class wins a tie
+    ///
+    pub fn argmax(probabilities: &DMatrix<f64>) -> Self {
+        Prediction::new(DVector::from_iterator(
+            probabilities.nrows(),
+            probabilities
+                .row_iter()
+                .map(|row| row.transpose().argmax().0),
+        ))
+    }
+}
+impl<T> IntoIterator for Prediction<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        Vec::from(self.inner.data).into_iter()
+    }
+}
+impl<'p, T> IntoIterator for &'p Prediction<T> {
+    type Item = &'p T;
+    type IntoIter = std::slice::Iter<'p, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<T> Prediction<T> {
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.inner.as_slice().iter()
+    }
+}
 impl<T> Prediction<T>
 where
     T: std::fmt::Display + std::fmt::Debug,
 {
-    fn new(vec: DVector<T>) -> Self {
+    pub(crate) fn new(vec: DVector<T>) -> Self {
         Prediction { inner: vec }
     }
-    pub fn show(&self, sample: usize) {
-        let sample = self.inner.iter().take(sample).collect::<Vec<&T>>();
-
-        let message = format!(
+    /// The first `n` predictions, in order
+    pub fn head(&self, n: usize) -> Vec<&T> {
+        self.inner.iter().take(n).collect()
+    }
+    /// The block `show` logs, for the caller to surface as it likes
+    pub fn summary_string(&self, n: usize) -> String {
+        format!(
             r#"
 -----------------------------------
 predictions: {:?}
 -----------------------------------
 "#,
-            &sample,
-        );
-        event!(Level::INFO, "{}", message);
+            self.head(n),
+        )
+    }
+    pub fn show(&self, sample: usize) {
+        event!(Level::INFO, "{}", self.summary_string(sample));
     }
 }
 
-// flatten the findig to bewteen 1, -1
+///
+/// The logistic function, squashing log-odds into (0, 1). Generic so that it
+/// maps straight over nalgebra's items; `sigmoid_f64` and `sigmoid_vec` are the
+/// plain forms and all three agree.
+///
 pub fn sigmoid<T>(v: T) -> f64
 where
-    T: Borrow<f64> + Neg<Output = f64> + PartialOrd<f64>,
+    T: Borrow<f64>,
 {
-    if v < -40. {
-        0.
-    } else if v > 40. {
-        1.
+    sigmoid_f64(*v.borrow())
+}
+
+/// `1 / (1 + e^-z)`, as `e^z / (1 + e^z)` for negative z so `exp` never overflows
+pub fn sigmoid_f64(z: f64) -> f64 {
+    if z >= 0.0 {
+        1.0 / (1.0 + (-z).exp())
     } else {
-        1. / (1. + f64::exp(-v))
+        let e = z.exp();
+        e / (1.0 + e)
+    }
+}
+
+/// `sigmoid_f64` of every element, e.g. the probabilities from `X * w`
+pub fn sigmoid_vec(v: &DVector<f64>) -> DVector<f64> {
+    v.map(sigmoid_f64)
+}
+
+///
+/// The bucket in `0..width` that feature hashing puts `value` in; FNV-1a, so a
+/// value lands in the same bucket on every run and platform, e.g. when scoring
+/// new records hashed like the training data
+///
+pub fn hash_bucket(value: &str, width: usize) -> usize {
+    let mut hasher = Fnv1a::new();
+    hasher.write(value.as_bytes());
+    // the low bits of FNV-1a only see the low bits of each byte; fold in the high ones
+    let hash = hasher.finish();
+    ((hash ^ (hash >> 32)) % width as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::{run, tests::toy};
+
+    /// A csv under the temp dir, unique to this process and `name`
+    fn write_csv(name: &str, contents: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("propensity-{}-{}.csv", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn from_csv_streaming_matches_from_csv() {
+        let mut contents = String::from("y,a,b\n");
+        for i in 0..10_000 {
+            contents.push_str(&format!(
+                "{},{},{}\n",
+                i % 3 % 2,
+                i as f64 / 7.0,
+                -(i as f64) / 13.0
+            ));
+        }
+        let path = write_csv("streaming", &contents);
+        let streamed = Objective::from_csv_streaming(&path, true).unwrap();
+        let staged = Objective::from_csv(&path, true).unwrap();
+        assert_eq!(streamed.x.nrows(), 10_000);
+        assert_eq!(streamed.x, staged.x);
+        assert_eq!(streamed.y, staged.y);
+        let ragged = write_csv("streaming-ragged", "y,a,b\n1,2.5,3\n0,-1\n");
+        assert!(Objective::from_csv_streaming(&ragged, true).is_err());
+        let garbled = write_csv("streaming-garbled", "y,a,b\n1,2.5,3\n0,x,1\n");
+        let error = Objective::from_csv_streaming(&garbled, true).unwrap_err();
+        assert!(
+            error.to_string().starts_with("Line 3, field 2"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn the_report_prints_the_odds_ratio_of_each_coefficient() {
+        let objective = toy().with_feature_names(vec!["dose".into()]).unwrap();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let report = findings.report().unwrap();
+        let beta = findings.coefficients[0];
+        assert!(report.contains(&format!("dose: {} (odds ratio: {},", beta, beta.exp())));
+        assert!(report.contains(&format!(
+            "intercept: {} (odds: {},",
+            findings.intercept,
+            findings.intercept.exp()
+        )));
+    }
+
+    #[test]
+    fn write_report_streams_what_report_returns() {
+        let objective = toy();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let mut buffer = Vec::new();
+        findings.write_report(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            findings.report().unwrap()
+        );
+        let cfg = CfgReport {
+            confidence_level: Some(0.9),
+            ..CfgReport::default()
+        };
+        let mut buffer = Vec::new();
+        findings.write_report_with(&mut buffer, &cfg).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            findings.report_with(&cfg).unwrap()
+        );
+    }
+
+    #[test]
+    fn a_prediction_iterates_by_reference_and_by_value() {
+        let prediction = Prediction::new(DVector::from_vec(vec![0.25, 0.5, 0.75]));
+        assert_eq!(prediction.iter().sum::<f64>(), 1.5);
+        let borrowed: Vec<&f64> = (&prediction).into_iter().collect();
+        assert_eq!(borrowed, vec![&0.25, &0.5, &0.75]);
+        let owned: Vec<f64> = prediction.into_iter().collect();
+        assert_eq!(owned, vec![0.25, 0.5, 0.75]);
+    }
+
+    #[test]
+    fn a_prediction_writes_one_value_per_line() {
+        let prediction = Prediction::new(DVector::from_vec(vec![0.25, 1.0]));
+        let path = write_csv("to-csv", "");
+        prediction.to_csv(&path, Some("p")).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "p\n0.25\n1\n");
+        prediction.to_csv(&path, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0.25\n1\n");
+
+        let ids = vec!["a".to_string(), "b".to_string()];
+        prediction
+            .to_csv_with_ids(&path, &ids, Some(("id", "p")))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "id,p\na,0.25\nb,1\n"
+        );
+        assert!(prediction.to_csv_with_ids(&path, &ids[..1], None).is_err());
+    }
+
+    #[test]
+    fn predict_both_agrees_with_predict_proba_and_threshold() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let (probabilities, labels) = findings.predict_both(0.3);
+        assert_eq!(probabilities.inner, findings.predict_proba().inner);
+        assert_eq!(labels.inner, probabilities.threshold(0.3).inner);
+        assert_eq!(
+            findings.predict_both(0.5).1.inner,
+            findings.predict(true).inner
+        );
+        assert!(
+            labels.iter().filter(|l| **l == 1.0).count()
+                > findings.predict(true).iter().filter(|l| **l == 1.0).count()
+        );
+    }
+
+    #[test]
+    fn a_two_valued_target_is_remapped_and_decoded() {
+        let y = DVector::from_vec(vec![2.0, 1.0, 2.0]);
+        let map = LabelMap::detect(&y).unwrap();
+        assert_eq!(
+            map,
+            LabelMap {
+                negative: 1.0,
+                positive: 2.0
+            }
+        );
+        assert_eq!((map.encode(2.0), map.encode(1.0)), (1.0, 0.0));
+        assert_eq!((map.decode(1.0), map.decode(0.0)), (2.0, 1.0));
+
+        assert!(LabelMap::detect(&DVector::from_vec(vec![1.0, 2.0, 3.0])).is_err());
+        assert!(LabelMap::detect(&DVector::from_vec(vec![1.0, 1.0])).is_err());
+        assert!(LabelMap::detect(&DVector::from_vec(vec![1.0, f64::NAN])).is_err());
+
+        let toy = toy();
+        let coded = toy.y.map(|v| if v == 1.0 { 7.0 } else { -3.0 });
+        let objective = Objective::new_remapped(toy.x.clone(), coded).unwrap();
+        assert_eq!(objective.y, toy.y);
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let original = findings.predict_original();
+        let labels = findings.predict(true);
+        for (o, l) in original.iter().zip(labels.iter()) {
+            assert_eq!(*o, if *l == 1.0 { 7.0 } else { -3.0 });
+        }
+    }
+
+    #[test]
+    fn the_decision_function_is_the_log_odds_of_predict_proba() {
+        let objective = toy();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let z = findings.decision_function();
+        assert_eq!(z.inner, &objective.x * &findings.all_betas);
+        assert_eq!(findings.predict_proba().inner, z.inner.map(sigmoid));
+        let new = DMatrix::from_row_slice(2, 2, &[0.0, 1.0, 2.0, 1.0]);
+        let on = findings.decision_function_on(&new).unwrap();
+        assert_eq!(on.inner[0], findings.intercept);
+        assert_eq!(
+            findings.predict_proba_on(&new).unwrap().inner,
+            on.inner.map(sigmoid)
+        );
+        assert!(findings
+            .decision_function_on(&DMatrix::zeros(1, 3))
+            .is_err());
+        assert!(findings.predict_proba_on(&DMatrix::zeros(1, 1)).is_err());
+    }
+
+    #[test]
+    fn the_report_prints_the_brier_score() {
+        let objective = toy();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let brier = brier_score(&objective.y, &findings.predict_proba()).unwrap();
+        assert!(findings
+            .report()
+            .unwrap()
+            .contains(&format!("Brier score: {}\n", brier)));
+    }
+
+    #[test]
+    fn fit_matches_logit_run() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let direct = logit::run(&objective, CfgBuilder::new().build()).unwrap();
+        assert_eq!(findings.all_betas, direct.all_betas);
+        assert_eq!(findings.final_nll, direct.final_nll);
+        // the positives cluster at large x
+        assert!(findings.coefficients[0] > 0.0);
+        assert_eq!(findings.intercept, findings.all_betas[1]);
+        assert!(findings.gradient_norm < 1e-4);
+    }
+
+    #[test]
+    fn from_csv_as_f32_widens_to_the_same_objective() {
+        let path = write_csv("from-csv-as", "y,a\n1,0.5\n0,-1.25\n");
+        let narrow = Objective::from_csv_as::<f32, _>(&path, true).unwrap();
+        let wide = Objective::from_csv(&path, true).unwrap();
+        assert_eq!(narrow.x, wide.x);
+        assert_eq!(narrow.y, wide.y);
+    }
+
+    #[test]
+    fn from_csv_as_rejects_a_file_without_records() {
+        let path = write_csv("from-csv-as-header-only", "y,a\n");
+        assert!(Objective::from_csv_as::<f32, _>(&path, true).is_err());
+    }
+
+    #[test]
+    fn from_csv_reads_target_predictors_and_intercept() {
+        let path = write_csv("from-csv", "y,a\n1,2.5\n0,-1\n");
+        let objective = Objective::from_csv(&path, true).unwrap();
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+        assert_eq!(
+            objective.x.row(0).iter().copied().collect::<Vec<_>>(),
+            vec![2.5, 1.0]
+        );
+        assert_eq!(
+            objective.x.row(1).iter().copied().collect::<Vec<_>>(),
+            vec![-1.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn fit_null_matches_the_intercept_only_fit() {
+        let y = [1.0, 0.0, 0.0, 1.0, 0.0];
+        let x = DMatrix::from_element(y.len(), 2, 1.0);
+        let objective = Objective::new(x.columns(1, 1).into_owned(), DVector::from_row_slice(&y));
+        let null = objective.fit_null().unwrap();
+        assert!((null.intercept - (2.0f64 / 3.0).ln()).abs() < 1e-12);
+        let nll = -(2.0 * 0.4f64.ln() + 3.0 * 0.6f64.ln());
+        assert!((null.final_nll - nll).abs() < 1e-12);
+        assert!(null.gradient_norm < 1e-12);
+        let fit = objective.fit(Cfg::default()).unwrap();
+        assert!((fit.intercept - null.intercept).abs() < 1e-4);
+    }
+
+    #[test]
+    fn fit_null_uses_the_weighted_base_rate() {
+        let y = DVector::from_row_slice(&[1.0, 0.0, 0.0, 1.0, 0.0]);
+        let w = DVector::from_row_slice(&[3.0, 1.0, 0.5, 2.0, 1.0]);
+        let objective = ObjectiveBuilder::new()
+            .x(DMatrix::from_element(5, 1, 1.0))
+            .y(y.clone())
+            .weights(w.clone())
+            .build()
+            .unwrap();
+        let null = objective.fit_null().unwrap();
+        let positives = w.dot(&y);
+        let negatives = w.dot(&y.map(|v| 1.0 - v));
+        assert!((null.intercept - (positives / negatives).ln()).abs() < 1e-12);
+        assert!(null.gradient_norm < 1e-12);
+    }
+
+    #[test]
+    fn fit_null_rejects_a_single_class() {
+        let x = DMatrix::from_element(3, 1, 1.0);
+        assert!(Objective::new(x.clone(), DVector::zeros(3))
+            .fit_null()
+            .is_err());
+        assert!(Objective::new(x, DVector::from_element(3, 1.0))
+            .fit_null()
+            .is_err());
+    }
+
+    fn builder() -> ObjectiveBuilder {
+        ObjectiveBuilder::new()
+            .x(DMatrix::from_row_slice(2, 2, &[2.0, 1.0, 3.0, 1.0]))
+            .y(DVector::from_vec(vec![1.0, 0.0]))
+    }
+
+    #[test]
+    fn builder_builds_with_names_weights_and_offset() {
+        let objective = builder()
+            .feature_names(vec!["a".into()])
+            .weights(DVector::from_vec(vec![2.0, 0.5]))
+            .offset(DVector::from_vec(vec![0.1, -0.1]))
+            .build()
+            .unwrap();
+        assert_eq!(objective.feature_names.unwrap(), vec!["a"]);
+        assert_eq!(objective.weights.unwrap().as_slice(), &[2.0, 0.5]);
+        assert_eq!(objective.offset.unwrap().as_slice(), &[0.1, -0.1]);
+    }
+
+    #[test]
+    fn builder_rejects_each_invalid_input() {
+        assert!(ObjectiveBuilder::new()
+            .y(DVector::from_vec(vec![1.0]))
+            .build()
+            .is_err());
+        assert!(ObjectiveBuilder::new()
+            .x(DMatrix::from_element(1, 1, 1.0))
+            .build()
+            .is_err());
+        assert!(builder().y(DVector::from_vec(vec![1.0])).build().is_err());
+        assert!(builder()
+            .y(DVector::from_vec(vec![1.0, 2.0]))
+            .build()
+            .is_err());
+        assert!(builder()
+            .x(DMatrix::from_row_slice(2, 2, &[f64::NAN, 1.0, 3.0, 1.0]))
+            .build()
+            .is_err());
+        assert!(builder().x(DMatrix::zeros(2, 0)).build().is_err());
+        assert!(builder()
+            .feature_names(vec!["a".into(), "b".into()])
+            .build()
+            .is_err());
+        assert!(builder()
+            .weights(DVector::from_vec(vec![1.0]))
+            .build()
+            .is_err());
+        assert!(builder()
+            .weights(DVector::from_vec(vec![1.0, -1.0]))
+            .build()
+            .is_err());
+        assert!(builder()
+            .offset(DVector::from_vec(vec![0.0, f64::NAN]))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn from_vecs_lays_x_out_by_row() {
+        let objective = Objective::from_vecs(vec![2.0, 1.0, 3.0, 1.0], vec![1.0, 0.0], 2).unwrap();
+        assert_eq!(
+            objective.x.row(1).iter().copied().collect::<Vec<_>>(),
+            vec![3.0, 1.0]
+        );
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+    }
+
+    #[test]
+    fn from_vecs_rejects_a_non_binary_target_and_non_finite_x() {
+        assert!(Objective::from_vecs(vec![2.0, 1.0, 3.0, 1.0], vec![2.0, 0.0], 2).is_err());
+        assert!(Objective::from_vecs(vec![f64::NAN, 1.0, 3.0, 1.0], vec![1.0, 0.0], 2).is_err());
+        assert!(Objective::from_vecs(vec![2.0, 1.0, 3.0], vec![1.0, 0.0], 2).is_err());
+    }
+
+    #[test]
+    fn from_csv_target_moves_the_named_column_to_the_target() {
+        let path = write_csv("from-csv-target", "a,y\n2.5,1\n-1,0\n");
+        let objective =
+            Objective::from_csv_target(&path, true, TargetSpec::Name("y".into())).unwrap();
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+        assert_eq!(
+            objective.x.column(0).iter().copied().collect::<Vec<_>>(),
+            vec![2.5, -1.0]
+        );
+    }
+
+    #[test]
+    fn from_csv_target_rejects_a_file_without_records() {
+        let path = write_csv("from-csv-target-header-only", "a,y\n");
+        assert!(Objective::from_csv_target(&path, true, TargetSpec::Name("y".into())).is_err());
+        let empty = write_csv("from-csv-target-empty", "");
+        assert!(Objective::from_csv_target(&empty, false, TargetSpec::Index(0)).is_err());
+    }
+
+    #[test]
+    fn from_csv_weighted_turns_the_frequency_column_into_weights() {
+        let path = write_csv("from-csv-weighted", "y,n,a\n1,3,2.5\n0,1,-1\n");
+        let objective = Objective::from_csv_weighted(&path, true, "n").unwrap();
+        assert_eq!(objective.weights.unwrap().as_slice(), &[3.0, 1.0]);
+        assert_eq!(objective.x.ncols(), 2);
+        assert_eq!(
+            objective.x.column(0).iter().copied().collect::<Vec<_>>(),
+            vec![2.5, -1.0]
+        );
+    }
+
+    #[test]
+    fn from_csv_weighted_fits_as_the_expanded_file_does() {
+        let rows = [
+            (1, 3, 2.5),
+            (0, 2, 2.5),
+            (0, 4, -1.0),
+            (1, 1, -1.0),
+            (1, 2, 0.5),
+            (0, 1, 0.5),
+        ];
+        let mut aggregated = String::from("y,n,a\n");
+        let mut expanded = String::from("y,a\n");
+        for (y, n, a) in rows {
+            aggregated.push_str(&format!("{},{},{}\n", y, n, a));
+            for _ in 0..n {
+                expanded.push_str(&format!("{},{}\n", y, a));
+            }
+        }
+        let aggregated = write_csv("from-csv-weighted-aggregated", &aggregated);
+        let expanded = write_csv("from-csv-weighted-expanded", &expanded);
+        let weighted = Objective::from_csv_weighted(&aggregated, true, "n").unwrap();
+        let full = Objective::from_csv(&expanded, true).unwrap();
+        assert_eq!(full.x.nrows(), 13);
+
+        let cfg = || CfgBuilder::new().gtol(1e-10).build();
+        let (weighted, full) = (weighted.fit(cfg()).unwrap(), full.fit(cfg()).unwrap());
+        assert!(
+            (&weighted.all_betas - &full.all_betas).amax() < 1e-6,
+            "{} vs {}",
+            weighted.all_betas,
+            full.all_betas
+        );
+        assert!((weighted.final_nll - full.final_nll).abs() < 1e-8);
+    }
+
+    #[test]
+    fn from_csv_weighted_rejects_a_count_that_is_not_positive() {
+        for (name, count) in [("zero", "0"), ("negative", "-2")] {
+            let path = write_csv(
+                &format!("from-csv-weighted-{}", name),
+                &format!("y,n,a\n1,3,2.5\n0,{},-1\n", count),
+            );
+            let err = Objective::from_csv_weighted(&path, true, "n").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("Frequencies must be positive; found {} in record 1", count)
+            );
+        }
+    }
+
+    #[test]
+    fn from_csv_weighted_rejects_a_file_without_records() {
+        let path = write_csv("from-csv-weighted-header-only", "y,n,a\n");
+        assert!(Objective::from_csv_weighted(&path, true, "n").is_err());
+    }
+
+    #[test]
+    fn from_csv_rejects_a_file_without_records() {
+        let header_only = write_csv("from-csv-header-only", "y,a\n");
+        assert!(Objective::from_csv(&header_only, true).is_err());
+        let empty = write_csv("from-csv-empty", "");
+        assert!(Objective::from_csv(&empty, false).is_err());
+    }
+
+    #[test]
+    fn cfg_predict_sets_the_default_prediction() {
+        let objective = toy();
+        let plain = objective.fit(CfgBuilder::new().build()).unwrap();
+        assert!(plain.default_prediction.is_none());
+
+        let cfg = CfgBuilder::new()
+            .with_predict(CfgPredict::new().binary_output(true).threshold(0.3))
+            .build();
+        let findings = objective.fit(cfg).unwrap();
+        let labels = findings.default_prediction.as_ref().unwrap();
+        assert_eq!(labels.inner, findings.predict_proba().threshold(0.3).inner);
+        assert!(labels.iter().all(|v| *v == 0.0 || *v == 1.0));
+        assert!(labels.iter().sum::<f64>() > 10.0);
+
+        let cfg = CfgBuilder::new().with_predict(CfgPredict::new()).build();
+        let findings = objective.fit(cfg).unwrap();
+        assert_eq!(
+            findings.default_prediction.as_ref().unwrap().inner,
+            findings.predict_proba().inner
+        );
+    }
+
+    #[test]
+    fn from_csv_xy_reads_x_and_y_from_separate_files() {
+        let x = write_csv("xy-x", "a,b\n1.5,2\n3,4\n5,6.5\n");
+        let y = write_csv("xy-y", "y\n0\n1\n1\n");
+        let objective = Objective::from_csv_xy(&x, &y, true).unwrap();
+        assert_eq!(
+            objective.x,
+            DMatrix::from_row_slice(3, 3, &[1.5, 2.0, 1.0, 3.0, 4.0, 1.0, 5.0, 6.5, 1.0])
+        );
+        assert_eq!(objective.y, DVector::from_row_slice(&[0.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn from_csv_xy_rejects_mismatched_files() {
+        let x = write_csv("xy-bad-x", "1,2\n3,4\n");
+        let short = write_csv("xy-short-y", "0\n");
+        let err = Objective::from_csv_xy(&x, &short, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The x file has 2 records but the y file has 1"
+        );
+
+        let wide = write_csv("xy-wide-y", "0,1\n1,0\n");
+        let err = Objective::from_csv_xy(&x, &wide, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The y file must hold a single column, got 2"
+        );
+
+        let labels = write_csv("xy-labels-y", "0\n2\n");
+        assert!(Objective::from_csv_xy(&x, &labels, false).is_err());
+    }
+
+    #[test]
+    fn prediction_display_shows_the_length_and_first_five() {
+        let short = Prediction::from(vec![0.25, 0.5]);
+        assert_eq!(short.to_string(), "Prediction of length 2: [0.25, 0.5]");
+        let long = Prediction::from((1..=7).map(f64::from).collect::<Vec<_>>());
+        assert_eq!(
+            long.to_string(),
+            "Prediction of length 7: [1, 2, 3, 4, 5, ...]"
+        );
+    }
+
+    #[test]
+    fn findings_display_reports_the_intercept_and_auc() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let auc = auc_score(&objective.y, &findings.predict_proba()).unwrap();
+        assert_eq!(
+            findings.to_string(),
+            format!(
+                "Findings with 1 features, intercept {} and AUC {}",
+                findings.intercept, auc
+            )
+        );
+    }
+
+    #[test]
+    fn summary_string_holds_the_head_that_show_logs() {
+        let prediction = Prediction::from(vec![0.1, 0.2, 0.3, 0.4]);
+        assert_eq!(prediction.head(2), [&0.1, &0.2]);
+        assert_eq!(prediction.head(10).len(), 4);
+        let summary = prediction.summary_string(3);
+        assert!(
+            summary.contains("predictions: [0.1, 0.2, 0.3]\n"),
+            "{summary}"
+        );
+        assert!(!summary.contains("0.4"));
+    }
+
+    #[test]
+    fn predictor_count_excludes_the_intercept_that_feature_count_includes() {
+        let path = write_csv("counts", "y,a,b,c\n0,1,2,3\n1,4,5,6\n");
+        let objective = Objective::from_csv(&path, true).unwrap();
+        assert_eq!(objective.feature_count(), 4);
+        assert_eq!(objective.predictor_count(), 3);
+        let err = Objective::from_csv(&path, true)
+            .unwrap()
+            .with_feature_names(vec!["a".into(), "b".into(), "c".into(), "d".into()])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected 3 feature names (excluding the intercept), got 4"
+        );
+        let named = objective
+            .with_feature_names(vec!["a".into(), "b".into(), "c".into()])
+            .unwrap();
+        assert_eq!(named.feature_name(2), "c");
+    }
+
+    #[test]
+    fn fit_predict_returns_the_in_sample_probabilities() {
+        let objective = toy();
+        let probabilities = objective.fit_predict(CfgBuilder::new().build()).unwrap();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        assert_eq!(probabilities.inner, findings.predict_proba().inner);
+        assert_eq!(probabilities.len(), 20);
+        // with an intercept the fitted probabilities sum to the positives
+        assert!((probabilities.sum() - 10.0).abs() < 1e-4);
+        assert!(probabilities[19] > probabilities[0]);
+    }
+
+    #[test]
+    fn predict_csv_streams_the_probabilities_of_predict_proba_on() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let rows: String = (0..3)
+            .map(|i| format!("{}\n", objective.x[(i * 9, 0)]))
+            .collect();
+        let input = write_csv("stream-in", &format!("x\n{}", rows));
+        let out =
+            std::env::temp_dir().join(format!("propensity-stream-out-{}.csv", std::process::id()));
+        findings.predict_csv(&input, true, &out).unwrap();
+
+        let written = std::fs::read_to_string(&out).unwrap();
+        let mut lines = written.lines();
+        assert_eq!(lines.next(), Some("probability"));
+        let streamed: Vec<f64> = lines.map(|l| l.parse().unwrap()).collect();
+        let expected = findings.predict_proba();
+        assert_eq!(streamed, [expected[0], expected[9], expected[18]]);
+    }
+
+    #[test]
+    fn predict_csv_rejects_a_record_of_the_wrong_width() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let input = write_csv("stream-wide", "0.5\n1,2\n");
+        let out = std::env::temp_dir().join(format!(
+            "propensity-stream-wide-out-{}.csv",
+            std::process::id()
+        ));
+        let err = findings.predict_csv(&input, false, &out).unwrap_err();
+        assert_eq!(err.to_string(), "Line 2 has 2 fields; expected 1");
+    }
+
+    #[test]
+    fn from_vec_checks_the_values_fill_the_rows() {
+        let objective = Objective::from_vec(vec![1.0, 2.0, 1.0, 0.0, 3.0, 1.0], 2).unwrap();
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+        assert_eq!(objective.x.column(0).as_slice(), &[2.0, 3.0]);
+
+        let err = Objective::from_vec(vec![1.0, 2.0, 1.0, 0.0, 3.0], 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The 5 values of data do not fill 2 rows evenly (1 left over)"
+        );
+        let err = Objective::from_vec(vec![1.0, 2.0], 0).unwrap_err();
+        assert_eq!(err.to_string(), "data can't be laid out in 0 rows");
+        let err = Objective::from_vecs(vec![2.0, 1.0, 3.0, 1.0], vec![1.0], 2).unwrap_err();
+        assert_eq!(err.to_string(), "Expected 2 target values, got 1");
+    }
+
+    #[test]
+    fn from_matrix_with_moves_a_leading_intercept_last() {
+        // target, intercept, then two predictors
+        let matrix = DMatrix::from_row_slice(2, 4, &[1.0, 1.0, 2.0, 3.0, 0.0, 1.0, 4.0, 5.0]);
+        let objective = Objective::from_matrix_with(matrix, InterceptPosition::First).unwrap();
+        assert_eq!(
+            objective.x,
+            DMatrix::from_row_slice(2, 3, &[2.0, 3.0, 1.0, 4.0, 5.0, 1.0])
+        );
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+
+        let last = DMatrix::from_row_slice(2, 3, &[1.0, 2.0, 1.0, 0.0, 4.0, 1.0]);
+        let objective = Objective::from_matrix_with(last.clone(), InterceptPosition::Last).unwrap();
+        assert_eq!(objective.x, last.remove_column(0));
+    }
+
+    #[test]
+    fn from_matrix_with_rejects_a_first_column_that_is_not_an_intercept() {
+        let matrix = DMatrix::from_row_slice(2, 3, &[1.0, 1.0, 2.0, 0.0, 0.5, 4.0]);
+        let err = Objective::from_matrix_with(matrix, InterceptPosition::First).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The first column is not an intercept: row 1 holds 0.5"
+        );
+    }
+
+    #[test]
+    fn predict_row_scores_a_record_like_predict_proba() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let probabilities = findings.predict_proba();
+        for i in [0, 7, 19] {
+            let p = findings
+                .predict_row(&[objective.x[(i, 0)]], false, 0.5)
+                .unwrap();
+            assert!((p - probabilities[i]).abs() < 1e-15);
+            let label = findings
+                .predict_row(&[objective.x[(i, 0)]], true, p)
+                .unwrap();
+            // labelled 1 only strictly above the threshold
+            assert_eq!(label, 0.0);
+        }
+        assert_eq!(findings.predict_row(&[10.0], true, 0.5).unwrap(), 1.0);
+        let err = findings.predict_row(&[1.0, 2.0], false, 0.5).unwrap_err();
+        assert_eq!(err.to_string(), "Expected 1 predictors, got 2");
+    }
+
+    #[test]
+    fn sigmoid_forms_agree_and_never_overflow() {
+        assert_eq!(sigmoid(0.0), 0.5);
+        assert!((sigmoid_f64(3f64.ln()) - 0.75).abs() < 1e-15);
+        assert!((sigmoid_f64(-3f64.ln()) - 0.25).abs() < 1e-15);
+        assert_eq!(sigmoid_f64(1000.0), 1.0);
+        assert_eq!(sigmoid_f64(-1000.0), 0.0);
+        assert!(sigmoid_f64(-700.0) > 0.0);
+
+        let z = DVector::from_vec(vec![-800.0, -2.0, 0.5, 40.0]);
+        let vectorized = sigmoid_vec(&z);
+        for (zi, pi) in z.iter().zip(vectorized.iter()) {
+            assert_eq!(*pi, sigmoid(*zi));
+            assert_eq!(*pi, sigmoid(zi));
+            assert!((pi + sigmoid_f64(-zi) - 1.0).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn nrows_positive_rate_and_class_counts_describe_the_target() {
+        let objective = toy();
+        assert_eq!(objective.nrows(), 20);
+        assert_eq!(objective.positive_rate(), 0.5);
+        assert_eq!(objective.class_counts(), (10, 10));
+
+        let rare = Objective::from_vecs(vec![1.0; 8], vec![0.0, 0.0, 0.0, 1.0], 4).unwrap();
+        assert_eq!(rare.nrows(), 4);
+        assert_eq!(rare.positive_rate(), 0.25);
+        assert_eq!(rare.class_counts(), (3, 1));
+    }
+
+    #[test]
+    fn summary_names_the_coefficients_and_scores_the_fit() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let summary = findings.summary().unwrap();
+        assert_eq!(summary.feature_names, ["x0"]);
+        assert_eq!(summary.coefficients, [findings.coefficients[0]]);
+        assert_eq!(summary.intercept, findings.intercept);
+        assert_eq!(
+            summary.auc,
+            auc_score(&objective.y, &findings.predict_proba()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn to_json_round_trips_the_summary() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let json = findings.to_json().unwrap();
+        assert!(
+            json.contains("\"feature_names\": [\n    \"x0\"\n  ]"),
+            "{json}"
+        );
+        let back: FindingsSummary = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, findings.summary().unwrap());
+    }
+
+    #[test]
+    fn partial_fit_moves_the_betas_towards_the_batch_optimum() {
+        let objective = toy();
+        let optimum = objective.fit(CfgBuilder::new().build()).unwrap();
+        let start = objective
+            .fit(CfgBuilder::new().max_iters(1).seed(3).build())
+            .unwrap();
+        let start_nll = start.final_nll;
+
+        let updated = start.partial_fit(&objective, 0.05, 200).unwrap();
+        assert!(updated.final_nll < start_nll);
+        assert!((updated.all_betas.clone() - &optimum.all_betas).amax() < 0.2);
+        assert!(matches!(
+            updated.cfg.solver,
+            SolverKind::Sgd { epochs: 200, .. }
+        ));
+        // chained, the state carries forward
+        let again = updated.partial_fit(&objective, 0.05, 200).unwrap();
+        assert!(again.final_nll - optimum.final_nll < 1e-2);
+    }
+
+    #[test]
+    fn partial_fit_over_streamed_batches_converges_near_the_full_fit() {
+        let objective = toy();
+        let optimum = objective.fit(CfgBuilder::new().build()).unwrap();
+        // four disjoint batches, each spanning the range of the predictor
+        let batches: Vec<Objective> = (0..4)
+            .map(|k| objective.subset(&(k..20).step_by(4).collect::<Vec<_>>()))
+            .collect();
+        let mut findings = objective
+            .fit(CfgBuilder::new().max_iters(1).seed(3).build())
+            .unwrap();
+        for _ in 0..50 {
+            for batch in &batches {
+                findings = findings.partial_fit(batch, 0.05, 5).unwrap();
+            }
+        }
+        let gap = (&findings.all_betas - &optimum.all_betas).amax();
+        assert!(gap < 0.1, "{} vs {}", findings.all_betas, optimum.all_betas);
+    }
+
+    #[test]
+    fn partial_fit_rejects_a_batch_of_another_width() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let wide = objective.expand_features(2, false);
+        let err = findings.partial_fit(&wide, 0.1, 1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The batch has 3 columns (intercept included) but the model 2"
+        );
+    }
+
+    #[test]
+    fn condition_number_is_one_for_orthogonal_columns_and_infinite_for_collinear() {
+        let signs = DMatrix::from_fn(4, 2, |i, j| if j == 0 && i % 2 == 0 { -1.0 } else { 1.0 });
+        let y = DVector::from_vec(vec![0.0, 1.0, 1.0, 0.0]);
+        let orthogonal = Objective::new(signs, y.clone());
+        assert!((orthogonal.condition_number() - 1.0).abs() < 1e-12);
+
+        // x only scaled: singular values sqrt(8) and sqrt(2) → ratio 2
+        let scaled = DMatrix::from_fn(4, 2, |i, j| match j {
+            0 if i % 2 == 0 => -2.0,
+            0 => 2.0,
+            _ => 1.0,
+        });
+        assert!((Objective::new(scaled, y.clone()).condition_number() - 2.0).abs() < 1e-12);
+
+        let twice = DMatrix::from_fn(4, 3, |i, j| if j < 2 { i as f64 } else { 1.0 });
+        assert_eq!(Objective::new(twice, y).condition_number(), f64::INFINITY);
+    }
+
+    #[test]
+    fn a_fit_on_collinear_predictors_carries_the_warning() {
+        let base = toy();
+        let x = DMatrix::from_fn(20, 3, |i, j| match j {
+            2 => 1.0,
+            _ => base.x[(i, 0)],
+        });
+        let collinear = Objective::new(x, base.y.clone());
+        let findings = collinear.fit(CfgBuilder::new().build()).unwrap();
+        assert!(findings.collinearity_warning);
+        let findings = base.fit(CfgBuilder::new().build()).unwrap();
+        assert!(!findings.collinearity_warning);
+    }
+
+    #[test]
+    fn vif_is_one_over_one_minus_r_squared() {
+        // the two predictors correlate at r = 0.8
+        let x = DMatrix::from_row_slice(
+            4,
+            3,
+            &[1.0, 1.0, 1.0, 2.0, 3.0, 1.0, 3.0, 2.0, 1.0, 4.0, 4.0, 1.0],
+        );
+        let objective = Objective::new(x, DVector::from_vec(vec![0.0, 1.0, 0.0, 1.0]));
+        let vif = objective.vif().unwrap();
+        assert_eq!(vif.len(), 2);
+        for (name, value) in &vif {
+            assert!(
+                (value - 1.0 / (1.0 - 0.64)).abs() < 1e-12,
+                "{name}: {value}"
+            );
+        }
+        let alone = toy().vif().unwrap();
+        assert_eq!(alone[0].0, "x0");
+        assert!((alone[0].1 - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn vif_flags_a_near_duplicate_but_not_an_independent_predictor() {
+        // x1 is x0 up to a small wobble; x2 is orthogonal to x0
+        let wobble = [0.01, -0.01, 0.0, 0.01, -0.01, 0.0, 0.01, -0.01];
+        let sign = [1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0];
+        let x = DMatrix::from_fn(8, 4, |i, j| match j {
+            0 => i as f64,
+            1 => i as f64 + wobble[i],
+            2 => sign[i],
+            _ => 1.0,
+        });
+        let objective = Objective::new(x, DVector::from_fn(8, |i, _| (i % 2) as f64));
+        let vif = objective.vif().unwrap();
+        assert!(vif[0].1 > 1000.0, "{:?}", vif);
+        assert!(vif[1].1 > 1000.0, "{:?}", vif);
+        assert!((vif[2].1 - 1.0).abs() < 0.05, "{:?}", vif);
+    }
+
+    #[test]
+    fn vif_rejects_constant_and_exactly_collinear_predictors() {
+        // a column of 0.1s, whose std rounds to a tiny nonzero value
+        let x = DMatrix::from_fn(3, 3, |i, j| match j {
+            0 => i as f64,
+            1 => 0.1,
+            _ => 1.0,
+        });
+        let constant = Objective::new(x, DVector::from_vec(vec![0.0, 1.0, 0.0]));
+        let err = constant.vif().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Predictor 'x1' is constant, so it has no vif"
+        );
+
+        let x = DMatrix::from_fn(3, 3, |i, j| if j < 2 { (i * (j + 1)) as f64 } else { 1.0 });
+        let collinear = Objective::new(x, DVector::from_vec(vec![0.0, 1.0, 0.0]));
+        assert!(collinear.vif().is_err());
+    }
+
+    #[test]
+    fn row_and_column_accessors_copy_the_predictors_only() {
+        let x = DMatrix::from_row_slice(2, 3, &[1.0, 2.0, 1.0, 3.0, 4.0, 1.0]);
+        let objective = Objective::new(x, DVector::from_vec(vec![1.0, 0.0]));
+        assert_eq!(objective.target().as_slice(), &[1.0, 0.0]);
+        assert_eq!(objective.feature_column(1).unwrap().as_slice(), &[2.0, 4.0]);
+        assert_eq!(objective.row(1).unwrap().as_slice(), &[3.0, 4.0]);
+
+        let err = objective.feature_column(2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Feature 2 is out of range for 2 predictors"
+        );
+        let err = objective.row(2).unwrap_err();
+        assert_eq!(err.to_string(), "Row 2 is out of range for 2 records");
+    }
+
+    #[test]
+    fn from_rows_pulls_the_target_out_of_each_row() {
+        let rows = vec![vec![2.0, 1.0, 3.0], vec![4.0, 0.0, 5.0]];
+        let objective = Objective::from_rows(rows.clone(), 1).unwrap();
+        assert_eq!(
+            objective.x,
+            DMatrix::from_row_slice(2, 3, &[2.0, 3.0, 1.0, 4.0, 5.0, 1.0])
+        );
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+
+        let first = Objective::try_from(vec![vec![1.0, 2.0], vec![0.0, 4.0]]).unwrap();
+        assert_eq!(first.y.as_slice(), &[1.0, 0.0]);
+        assert_eq!(first.x.column(0).as_slice(), &[2.0, 4.0]);
+    }
+
+    #[test]
+    fn from_rows_rejects_ragged_and_empty_rows() {
+        let err = Objective::from_rows(vec![vec![1.0, 2.0], vec![0.0]], 0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Row 1 holds 1 values; the first row holds 2"
+        );
+        let err = Objective::from_rows(vec![], 0).unwrap_err();
+        assert_eq!(err.to_string(), "The rows hold no values");
+        let err = Objective::from_rows(vec![vec![1.0, 2.0]], 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Target column 2 is out of range for rows of 2 values"
+        );
+    }
+
+    #[test]
+    fn the_report_shows_the_class_balance_and_base_rate() {
+        let objective = Objective::from_rows(
+            vec![
+                vec![0.0, 1.0],
+                vec![0.0, 2.0],
+                vec![1.0, 2.5],
+                vec![0.0, 3.0],
+                vec![1.0, 3.5],
+                vec![0.0, 1.5],
+                vec![1.0, 1.8],
+                vec![0.0, 4.0],
+            ],
+            0,
+        )
+        .unwrap();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let report = findings.report().unwrap();
+        assert!(
+            report.contains("positives: 3\nnegatives: 5\nbase rate: 0.375\n"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn the_report_weighs_the_base_rate() {
+        let objective = ObjectiveBuilder::new()
+            .x(DMatrix::from_element(4, 1, 1.0))
+            .y(DVector::from_row_slice(&[1.0, 0.0, 0.0, 1.0]))
+            .weights(DVector::from_row_slice(&[3.0, 1.0, 2.0, 2.0]))
+            .build()
+            .unwrap();
+        let report = objective.fit_null().unwrap().report().unwrap();
+        assert!(
+            report.contains("positives: 2\nnegatives: 2\nbase rate: 0.625\n"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn threshold_labels_above_and_argmax_picks_the_first_largest_class() {
+        let probabilities = Prediction::from(vec![0.2, 0.5, 0.7]);
+        assert_eq!(probabilities.threshold(0.5).as_slice(), &[0.0, 0.0, 1.0]);
+        assert_eq!(probabilities.threshold(0.1).as_slice(), &[1.0, 1.0, 1.0]);
+
+        let by_class =
+            DMatrix::from_row_slice(3, 3, &[0.1, 0.7, 0.2, 0.5, 0.25, 0.25, 0.4, 0.2, 0.4]);
+        let classes: Vec<usize> = Prediction::argmax(&by_class).into();
+        assert_eq!(classes, [1, 0, 0]);
+    }
+
+    #[test]
+    fn predict_proba_batched_matches_predict_proba_on_for_any_batch() {
+        let objective = toy();
+        let findings = objective.fit(CfgBuilder::new().build()).unwrap();
+        let whole = findings.predict_proba_on(&objective.x).unwrap();
+        // a batch that divides the rows, one that leaves a short last batch, one too big
+        for batch_rows in [1, 4, 7, 50] {
+            let batched = findings
+                .predict_proba_batched(&objective.x, batch_rows)
+                .unwrap();
+            assert_eq!(batched.inner, whole.inner, "batch of {batch_rows}");
+        }
+        let err = findings.predict_proba_batched(&objective.x, 0).unwrap_err();
+        assert_eq!(err.to_string(), "The batch needs at least 1 row");
+        assert!(findings
+            .predict_proba_batched(&DMatrix::zeros(2, 3), 1)
+            .is_err());
     }
 }
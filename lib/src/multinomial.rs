@@ -0,0 +1,318 @@
+use argmin::core::{CostFunction, Error, Gradient, TerminationReason};
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+use tracing::{event, Level};
+
+use crate::configurations::{Cfg, SolverKind};
+use crate::link::Link;
+use crate::logit::{check_param_len, minimize, Design};
+use crate::models::Prediction;
+
+///
+/// Softmax (multinomial) logistic regression for more than two classes. `y`
+/// holds the class of each record, `0..classes`; as with `Objective` the
+/// intercept is the last column of x.
+///
+/// The param is the `classes x p` coefficient matrix flattened column by column
+/// (nalgebra's storage order), so the LBFGS machinery of `logit` is reused as is.
+///
+#[derive(Debug)]
+pub struct Multinomial {
+    pub x: DMatrix<f64>,
+    pub y: Vec<usize>,
+    pub classes: usize,
+}
+
+impl Multinomial {
+    pub fn new(x: DMatrix<f64>, y: Vec<usize>) -> Result<Self> {
+        if x.nrows() != y.len() {
+            return Err(eyre!(
+                "x has {} rows but y has {} values",
+                x.nrows(),
+                y.len()
+            ));
+        }
+        let classes = y.iter().max().map_or(0, |max| max + 1);
+        if classes < 2 {
+            return Err(eyre!("Expected at least two classes, got {}", classes));
+        }
+        Ok(Multinomial { x, y, classes })
+    }
+    pub fn feature_count(&self) -> usize {
+        self.x.ncols()
+    }
+    /// flattened param -> `classes x p`
+    fn coefficients(&self, param: &DVector<f64>) -> DMatrix<f64> {
+        DMatrix::from_column_slice(self.classes, self.feature_count(), param.as_slice())
+    }
+    /// The row-wise softmax of `X * W'`, one column per class
+    fn probabilities(&self, param: &DVector<f64>) -> DMatrix<f64> {
+        softmax(&self.x * self.coefficients(param).transpose())
+    }
+}
+
+impl Design for Multinomial {
+    fn feature_count(&self) -> usize {
+        Multinomial::feature_count(self)
+    }
+    fn record_count(&self) -> usize {
+        self.x.nrows()
+    }
+    fn param_len(&self) -> usize {
+        self.classes * self.feature_count()
+    }
+}
+
+/// Softmax cross-entropy
+impl CostFunction for &Multinomial {
+    type Param = DVector<f64>;
+    type Output = f64;
+
+    fn cost(&self, param: &Self::Param) -> Result<Self::Output, Error> {
+        check_param_len(self.param_len(), param)?;
+        let probabilities = self.probabilities(param);
+        let ll: f64 = self
+            .y
+            .iter()
+            .enumerate()
+            .map(|(i, &k)| probabilities[(i, k)].max(f64::MIN_POSITIVE).ln())
+            .sum();
+
+        Ok(-ll)
+    }
+}
+
+/// `(P - Y_onehot)' * X`, flattened the same way as the param
+impl Gradient for &Multinomial {
+    type Param = DVector<f64>;
+    type Gradient = DVector<f64>;
+
+    fn gradient(&self, param: &Self::Param) -> Result<Self::Gradient, Error> {
+        check_param_len(self.param_len(), param)?;
+        let mut residuals = self.probabilities(param);
+        for (i, &k) in self.y.iter().enumerate() {
+            residuals[(i, k)] -= 1.0;
+        }
+        let gradient = residuals.tr_mul(&self.x);
+
+        Ok(DVector::from_column_slice(gradient.as_slice()))
+    }
+}
+
+///
+/// Findings from a multinomial fit. Lifetime is tied to objective.
+///
+#[derive(Debug)]
+pub struct MultinomialFindings<'a> {
+    /// `classes x p`; one row per class, the intercept in the last column
+    pub coefficients: DMatrix<f64>,
+    pub objective: &'a Multinomial,
+    /// the configuration used to derive the findings
+    pub cfg: Cfg,
+    /// the softmax cross-entropy at the fitted coefficients
+    pub final_nll: f64,
+    /// the cost after each iteration of the solver
+    pub cost_history: Vec<f64>,
+    /// the fit hit `Cfg::max_duration`; the coefficients are the best found so far
+    pub timed_out: bool,
+    /// how many iterations the solver ran
+    pub iterations: u64,
+    /// why the solver stopped
+    pub termination: TerminationReason,
+}
+
+impl MultinomialFindings<'_> {
+    /// One row per record, one column per class; each row sums to 1
+    pub fn predict_proba(&self) -> DMatrix<f64> {
+        softmax(&self.objective.x * self.coefficients.transpose())
+    }
+    /// The most probable class of each record
+    pub fn predict(&self) -> Vec<usize> {
+        Prediction::argmax(&self.predict_proba()).into()
+    }
+    /// Share of the records where the predicted class is the observed one
+    pub fn accuracy(&self) -> f64 {
+        let hits = self
+            .predict()
+            .iter()
+            .zip(&self.objective.y)
+            .filter(|(y_hat, y)| y_hat == y)
+            .count();
+        hits as f64 / self.objective.y.len() as f64
+    }
+}
+
+pub fn run(objective: &Multinomial, cfg: Cfg) -> Result<MultinomialFindings<'_>> {
+    event!(
+        Level::INFO,
+        "🟢 Running the multinomial optimization with {} classes and feature count: {}",
+        objective.classes,
+        objective.feature_count()
+    );
+
+    cfg.check_penalties()?;
+    check_cfg(&cfg)?;
+    let fit = minimize(objective, &cfg, None)?;
+
+    Ok(MultinomialFindings {
+        coefficients: objective.coefficients(&fit.param),
+        objective,
+        cfg,
+        final_nll: fit.cost,
+        timed_out: fit.timed_out(),
+        cost_history: fit.cost_history,
+        iterations: fit.iterations,
+        termination: fit.termination,
+    })
+}
+
+/// `run` fits by LBFGS alone; a `cfg` asking for more is an error, not ignored
+fn check_cfg(cfg: &Cfg) -> Result<()> {
+    match cfg.solver {
+        SolverKind::Newton => {
+            return Err(eyre!(
+                "The multinomial fit has no Hessian for SolverKind::Newton; use SolverKind::Lbfgs"
+            ))
+        }
+        SolverKind::Sgd { .. } => {
+            return Err(eyre!(
+            "The multinomial fit has no batch gradient for SolverKind::Sgd; use SolverKind::Lbfgs"
+        ))
+        }
+        SolverKind::Lbfgs | SolverKind::OwlQn => {}
+    }
+    if cfg.link != Link::Logit {
+        return Err(eyre!(
+            "The multinomial fit is softmax only; {:?} isn't supported",
+            cfg.link
+        ));
+    }
+    // argmin's OWL-QN would penalize every class's intercept too
+    if cfg.l1 != 0.0 {
+        return Err(eyre!(
+            "The multinomial fit has no l1 penalty; got {}",
+            cfg.l1
+        ));
+    }
+    if cfg.l2 != 0.0 {
+        return Err(eyre!(
+            "The multinomial fit has no l2 penalty; got {}",
+            cfg.l2
+        ));
+    }
+    Ok(())
+}
+
+/// Row-wise softmax, shifted by the row max to avoid overflow
+fn softmax(mut z: DMatrix<f64>) -> DMatrix<f64> {
+    for mut row in z.row_iter_mut() {
+        let max = row.max();
+        row.apply(|v| *v = (*v - max).exp());
+        let total = row.sum();
+        row /= total;
+    }
+    z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+
+    /// one predictor (then the intercept) that orders the three classes, with overlap
+    fn three_classes() -> Multinomial {
+        let xs = [
+            -2.0, -1.5, -1.0, 0.2, -0.5, 0.0, 0.5, 1.2, 1.0, 1.5, 2.0, 0.8,
+        ];
+        let y = vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2];
+        let x = DMatrix::from_fn(xs.len(), 2, |i, j| if j == 0 { xs[i] } else { 1.0 });
+        Multinomial::new(x, y).unwrap()
+    }
+
+    #[test]
+    fn run_fits_probabilities_that_sum_to_one() {
+        let objective = three_classes();
+        let findings = run(&objective, CfgBuilder::new().build()).unwrap();
+        let probabilities = findings.predict_proba();
+        for row in probabilities.row_iter() {
+            assert!((row.sum() - 1.0).abs() < 1e-12);
+        }
+        // the slopes order the classes as the predictor does
+        let slopes = findings.coefficients.column(0);
+        assert!(slopes[0] < slopes[1] && slopes[1] < slopes[2]);
+        // at the optimum the intercepts match each class's expected count to its observed 4
+        for column in probabilities.column_iter() {
+            assert!((column.sum() - 4.0).abs() < 1e-3);
+        }
+        assert_eq!(findings.predict()[0], 0);
+        assert_eq!(findings.predict()[10], 2);
+    }
+
+    #[test]
+    fn run_rejects_sgd() {
+        let objective = three_classes();
+        let cfg = CfgBuilder::new()
+            .solver(SolverKind::Sgd {
+                batch_size: 4,
+                learning_rate: 0.1,
+                epochs: 10,
+                momentum: 0.0,
+            })
+            .build();
+        assert!(run(&objective, cfg).is_err());
+    }
+
+    #[test]
+    fn run_rejects_an_l2_penalty() {
+        let objective = three_classes();
+        assert!(run(&objective, CfgBuilder::new().l2(0.5).build()).is_err());
+    }
+
+    #[test]
+    fn run_rejects_the_probit_link() {
+        let objective = three_classes();
+        assert!(run(&objective, CfgBuilder::new().link(Link::Probit).build()).is_err());
+    }
+
+    #[test]
+    fn run_rejects_an_l1_penalty() {
+        let objective = three_classes();
+        let cfg = CfgBuilder::new().solver(SolverKind::OwlQn).l1(0.5).build();
+        assert!(run(&objective, cfg).is_err());
+    }
+
+    #[test]
+    fn run_rejects_newton() {
+        let objective = three_classes();
+        let cfg = CfgBuilder::new().solver(SolverKind::Newton).build();
+        assert!(run(&objective, cfg).is_err());
+    }
+
+    #[test]
+    fn the_softmax_gradient_matches_central_differences() {
+        let objective = three_classes();
+        let param = DVector::from_fn(objective.param_len(), |i, _| 0.3 * i as f64 - 0.7);
+        let gradient = (&objective).gradient(&param).unwrap();
+        let eps = 1e-6;
+        for k in 0..param.len() {
+            let mut up = param.clone();
+            up[k] += eps;
+            let mut down = param.clone();
+            down[k] -= eps;
+            let numeric =
+                ((&objective).cost(&up).unwrap() - (&objective).cost(&down).unwrap()) / (2.0 * eps);
+            assert!((gradient[k] - numeric).abs() < 1e-6);
+        }
+        // at zero every class is equally likely, so the cost is n ln 3
+        let zero = DVector::zeros(objective.param_len());
+        assert!(((&objective).cost(&zero).unwrap() - 12.0 * 3f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn new_needs_matching_rows_and_two_classes() {
+        let x = DMatrix::from_element(3, 1, 1.0);
+        assert!(Multinomial::new(x.clone(), vec![0, 1]).is_err());
+        assert!(Multinomial::new(x.clone(), vec![0, 0, 0]).is_err());
+        assert_eq!(Multinomial::new(x, vec![0, 2, 1]).unwrap().classes, 3);
+    }
+}
@@ -0,0 +1,61 @@
+use color_eyre::eyre::Result;
+use nalgebra::base::{DMatrix, DVector};
+use ndarray::{Array1, Array2};
+
+use crate::models::{Objective, ObjectiveBuilder};
+
+impl Objective {
+    ///
+    /// ndarray -> Objective; x holds the features only, the intercept slot is
+    /// appended as the last column.
+    ///
+    pub fn from_ndarray(x: Array2<f64>, y: Array1<f64>) -> Result<Self> {
+        let (rows, cols) = x.dim();
+        let x = DMatrix::from_fn(
+            rows,
+            cols + 1,
+            |i, j| if j < cols { x[[i, j]] } else { 1.0 },
+        );
+        let y = DVector::from_iterator(y.len(), y);
+
+        ObjectiveBuilder::new().x(x).y(y).build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn from_ndarray_appends_the_intercept() {
+        let objective = Objective::from_ndarray(array![[2.0], [3.0]], array![1.0, 0.0]).unwrap();
+        assert_eq!(
+            objective.x.row(1).iter().copied().collect::<Vec<_>>(),
+            vec![3.0, 1.0]
+        );
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+    }
+
+    #[test]
+    fn from_ndarray_validates_like_the_builder() {
+        assert!(Objective::from_ndarray(array![[2.0], [3.0]], array![1.0]).is_err());
+        assert!(Objective::from_ndarray(array![[2.0], [3.0]], array![1.0, 2.0]).is_err());
+        assert!(Objective::from_ndarray(array![[f64::NAN], [3.0]], array![1.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn from_ndarray_lays_out_x_like_from_vecs() {
+        let x = array![[1.0, 4.0], [2.0, 5.0], [3.0, 6.0]];
+        let objective = Objective::from_ndarray(x, array![0.0, 1.0, 1.0]).unwrap();
+        let expected = Objective::from_vecs(
+            vec![1.0, 4.0, 1.0, 2.0, 5.0, 1.0, 3.0, 6.0, 1.0],
+            vec![0.0, 1.0, 1.0],
+            3,
+        )
+        .unwrap();
+        assert_eq!(objective.x, expected.x);
+        assert_eq!(objective.y, expected.y);
+        assert_eq!(objective.predictor_count(), 2);
+    }
+}
@@ -0,0 +1,119 @@
+use argmin::core::observers::Observe;
+use argmin::core::{Error, State, KV};
+
+use std::sync::{Arc, Mutex};
+
+use crate::configurations::IterationCallback;
+
+///
+/// Records the cost at every iteration. The executor takes ownership of its
+/// observers, so the history is read back through a clone of the handle.
+///
+#[derive(Clone, Default)]
+pub(crate) struct CostHistory(Arc<Mutex<Vec<f64>>>);
+
+impl CostHistory {
+    pub(crate) fn take(&self) -> Vec<f64> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl<I: State<Float = f64>> Observe<I> for CostHistory {
+    fn observe_iter(&mut self, state: &I, _kv: &KV) -> Result<(), Error> {
+        self.0.lock().unwrap().push(state.get_cost());
+        Ok(())
+    }
+}
+
+impl<I: State<Float = f64>> Observe<I> for IterationCallback {
+    fn observe_iter(&mut self, state: &I, _kv: &KV) -> Result<(), Error> {
+        (self.0.lock().unwrap())(state.get_iter(), state.get_cost());
+        Ok(())
+    }
+}
+
+///
+/// A terminal progress bar advanced every iteration. When logging is also on the
+/// log lines are printed above the bar rather than by a second observer, which
+/// would draw over it.
+///
+#[cfg(feature = "progress")]
+#[derive(Clone)]
+pub(crate) struct Progress {
+    bar: indicatif::ProgressBar,
+    logging: bool,
+}
+
+#[cfg(feature = "progress")]
+impl Progress {
+    pub(crate) fn new(max_iters: u64, logging: bool) -> Self {
+        let bar = indicatif::ProgressBar::new(max_iters);
+        if let Ok(style) =
+            indicatif::ProgressStyle::with_template("{bar:40} {pos}/{len} iters {msg}")
+        {
+            bar.set_style(style);
+        }
+        Progress { bar, logging }
+    }
+    pub(crate) fn finish(&self) {
+        self.bar.finish();
+    }
+}
+
+#[cfg(feature = "progress")]
+impl<I: State<Float = f64>> Observe<I> for Progress {
+    fn observe_iter(&mut self, state: &I, _kv: &KV) -> Result<(), Error> {
+        self.bar.set_position(state.get_iter());
+        self.bar
+            .set_message(format!("cost: {:.6}", state.get_cost()));
+        if self.logging {
+            self.bar.println(format!(
+                "iter: {} cost: {} best cost: {}",
+                state.get_iter(),
+                state.get_cost(),
+                state.get_best_cost()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "progress"))]
+mod tests {
+    use super::*;
+    use argmin::core::{IterState, State};
+    use nalgebra::DVector;
+
+    use crate::configurations::CfgBuilder;
+    use crate::logit::tests::toy;
+
+    type Iter = IterState<DVector<f64>, DVector<f64>, (), (), f64>;
+
+    #[test]
+    fn progress_tracks_the_iteration_and_cost() {
+        let progress = Progress::new(50, false);
+        assert_eq!(progress.bar.length(), Some(50));
+
+        let mut observer = progress.clone();
+        let mut state = Iter::new().cost(0.25);
+        state.increment_iter();
+        state.increment_iter();
+        observer.observe_iter(&state, &KV::new()).unwrap();
+        assert_eq!(progress.bar.position(), 2);
+        assert_eq!(progress.bar.message(), "cost: 0.250000");
+
+        progress.finish();
+        assert!(progress.bar.is_finished());
+    }
+
+    #[test]
+    fn a_fit_with_progress_matches_one_without() {
+        let objective = toy();
+        let plain = objective.fit(CfgBuilder::new().build()).unwrap();
+        let shown = objective
+            .fit(CfgBuilder::new().progress(true).build())
+            .unwrap();
+        assert_eq!(plain.all_betas, shown.all_betas);
+        assert_eq!(plain.iterations, shown.iterations);
+    }
+}
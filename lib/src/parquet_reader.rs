@@ -0,0 +1,141 @@
+use arrow_array::cast::AsArray;
+use arrow_array::types::Float64Type;
+use arrow_array::Array;
+use arrow_cast::cast;
+use arrow_schema::DataType;
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use std::fs::File;
+use std::path::Path;
+
+use crate::models::{check_binary, check_finite, Objective, TargetSpec};
+
+impl Objective {
+    ///
+    /// Parquet -> Objective. Every column must be numeric; the target is picked out
+    /// with `target`, the remaining columns keep their order, become the feature
+    /// names, and are followed by the intercept slot.
+    ///
+    pub fn from_parquet<P: AsRef<Path>>(path: P, target: TargetSpec) -> Result<Self> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+
+        let columns: Vec<String> = builder
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| {
+                if field.data_type().is_numeric() {
+                    Ok(field.name().clone())
+                } else {
+                    Err(eyre!(
+                        "Column '{}' is not numeric ({})",
+                        field.name(),
+                        field.data_type()
+                    ))
+                }
+            })
+            .collect::<Result<_>>()?;
+        let target_col = target.resolve(&columns)?;
+        let num_rows = builder.metadata().file_metadata().num_rows() as usize;
+
+        // features + intercept slot
+        let mut x = DMatrix::from_element(num_rows, columns.len(), 1.0);
+        let mut y = DVector::zeros(num_rows);
+
+        let mut offset = 0;
+        for batch in builder.build()? {
+            let batch = batch?;
+            for (c, column) in batch.columns().iter().enumerate() {
+                if column.null_count() > 0 {
+                    return Err(eyre!("Column '{}' has missing values", columns[c]));
+                }
+                let values = cast(column, &DataType::Float64)?;
+                let values = values.as_primitive::<Float64Type>().values();
+                for (i, v) in values.iter().enumerate() {
+                    match c.cmp(&target_col) {
+                        std::cmp::Ordering::Equal => y[offset + i] = *v,
+                        std::cmp::Ordering::Less => x[(offset + i, c)] = *v,
+                        std::cmp::Ordering::Greater => x[(offset + i, c - 1)] = *v,
+                    }
+                }
+            }
+            offset += batch.num_rows();
+        }
+
+        check_binary(&y).map_err(|e| eyre!("Target column '{}': {}", columns[target_col], e))?;
+        check_finite(&x)?;
+
+        let mut feature_names = columns;
+        feature_names.remove(target_col);
+        Objective::new(x, y).with_feature_names(feature_names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{ArrayRef, Float64Array, Int32Array, RecordBatch, StringArray};
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    fn write_parquet(name: &str, columns: Vec<(&str, ArrayRef)>) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("parquet-{}-{}.parquet", name, std::process::id()));
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        path
+    }
+
+    #[test]
+    fn from_parquet_picks_out_the_target_and_names_the_rest() {
+        let path = write_parquet(
+            "target",
+            vec![
+                (
+                    "a",
+                    Arc::new(Float64Array::from(vec![2.5, -1.0])) as ArrayRef,
+                ),
+                ("y", Arc::new(Int32Array::from(vec![1, 0])) as ArrayRef),
+                ("b", Arc::new(Int32Array::from(vec![7, 9])) as ArrayRef),
+            ],
+        );
+        let objective = Objective::from_parquet(&path, TargetSpec::Name("y".into())).unwrap();
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+        assert_eq!(
+            objective.x.row(0).iter().copied().collect::<Vec<_>>(),
+            vec![2.5, 7.0, 1.0]
+        );
+        assert_eq!(objective.feature_names.unwrap(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn from_parquet_rejects_strings_and_a_non_binary_target() {
+        let strings = write_parquet(
+            "strings",
+            vec![
+                ("y", Arc::new(Int32Array::from(vec![1, 0])) as ArrayRef),
+                (
+                    "city",
+                    Arc::new(StringArray::from(vec!["oslo", "rome"])) as ArrayRef,
+                ),
+            ],
+        );
+        assert!(Objective::from_parquet(&strings, TargetSpec::Index(0)).is_err());
+        let counts = write_parquet(
+            "counts",
+            vec![
+                ("y", Arc::new(Int32Array::from(vec![3, 0])) as ArrayRef),
+                (
+                    "a",
+                    Arc::new(Float64Array::from(vec![1.0, 2.0])) as ArrayRef,
+                ),
+            ],
+        );
+        assert!(Objective::from_parquet(&counts, TargetSpec::Index(0)).is_err());
+    }
+}
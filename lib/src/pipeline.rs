@@ -0,0 +1,89 @@
+use color_eyre::eyre::Result;
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::configurations::Cfg;
+use crate::models::{Objective, Prediction};
+
+///
+/// Wall-clock spent in each phase of `run_pipeline`, in milliseconds
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Timings {
+    /// reading the csv into an `Objective`
+    pub load_ms: f64,
+    /// the solver
+    pub fit_ms: f64,
+    /// scoring the training records
+    pub predict_ms: f64,
+}
+
+impl Timings {
+    pub fn total_ms(&self) -> f64 {
+        self.load_ms + self.fit_ms + self.predict_ms
+    }
+}
+
+///
+/// csv -> fit -> probabilities, timing each phase; tells whether the IO or the
+/// solver is worth optimizing. The csv has the `Objective::from_csv` layout.
+///
+pub fn run_pipeline<P: AsRef<Path>>(
+    path: P,
+    with_headers: bool,
+    cfg: Cfg,
+) -> Result<(Prediction<f64>, Timings)> {
+    let start = Instant::now();
+    let objective = Objective::from_csv(path, with_headers)?;
+    let load_ms = millis(start.elapsed());
+
+    let start = Instant::now();
+    let findings = objective.fit(cfg)?;
+    let fit_ms = millis(start.elapsed());
+
+    let start = Instant::now();
+    let prediction = findings.predict_proba();
+    let predict_ms = millis(start.elapsed());
+
+    Ok((
+        prediction,
+        Timings {
+            load_ms,
+            fit_ms,
+            predict_ms,
+        },
+    ))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+
+    #[test]
+    fn run_pipeline_predicts_like_a_fit_and_times_each_phase() {
+        let path = std::env::temp_dir().join(format!("pipeline-{}.csv", std::process::id()));
+        let rows: String = [(0, 1.0), (0, 2.0), (1, 2.5), (0, 3.0), (1, 3.5), (1, 4.0)]
+            .iter()
+            .map(|(y, x)| format!("{},{}\n", y, x))
+            .collect();
+        std::fs::write(&path, format!("y,x\n{}", rows)).unwrap();
+
+        let (prediction, timings) = run_pipeline(&path, true, CfgBuilder::new().build()).unwrap();
+        let objective = Objective::from_csv(&path, true).unwrap();
+        let expected = objective.fit_predict(CfgBuilder::new().build()).unwrap();
+        assert_eq!(prediction.inner, expected.inner);
+
+        assert!(timings.load_ms > 0.0 && timings.fit_ms > 0.0 && timings.predict_ms >= 0.0);
+        assert_eq!(
+            timings.total_ms(),
+            timings.load_ms + timings.fit_ms + timings.predict_ms
+        );
+        assert_eq!(millis(Duration::from_micros(1500)), 1.5);
+    }
+}
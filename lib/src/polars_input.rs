@@ -0,0 +1,93 @@
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+use polars::prelude::{DataFrame, DataType};
+
+use crate::models::{Objective, ObjectiveBuilder};
+
+impl Objective {
+    ///
+    /// DataFrame -> Objective using the named columns; the features keep the
+    /// order given, are followed by the intercept slot, and their names are kept
+    /// for the report.
+    ///
+    pub fn from_dataframe(df: &DataFrame, target: &str, features: &[&str]) -> Result<Self> {
+        let column_f64 = |name: &str| -> Result<Vec<f64>> {
+            let column = df
+                .column(name)
+                .map_err(|_| eyre!("Column '{}' not found", name))?;
+            if !column.dtype().is_primitive_numeric() {
+                return Err(eyre!(
+                    "Column '{}' is not numeric ({})",
+                    name,
+                    column.dtype()
+                ));
+            }
+            column
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .map(|v| v.ok_or_else(|| eyre!("Column '{}' has missing values", name)))
+                .collect()
+        };
+
+        let rows = df.height();
+        let mut x = DMatrix::from_element(rows, features.len() + 1, 1.0);
+        for (j, name) in features.iter().enumerate() {
+            for (i, v) in column_f64(name)?.into_iter().enumerate() {
+                x[(i, j)] = v;
+            }
+        }
+        let y = DVector::from_vec(column_f64(target)?);
+
+        ObjectiveBuilder::new()
+            .x(x)
+            .y(y)
+            .feature_names(features.iter().map(|f| f.to_string()).collect())
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::df;
+
+    #[test]
+    fn from_dataframe_keeps_the_feature_order_and_names() {
+        let df = df!("y" => [1.0, 0.0], "a" => [2.0, 3.0], "b" => [5i32, 7]).unwrap();
+        let objective = Objective::from_dataframe(&df, "y", &["b", "a"]).unwrap();
+        assert_eq!(
+            objective.x.row(0).iter().copied().collect::<Vec<_>>(),
+            vec![5.0, 2.0, 1.0]
+        );
+        assert_eq!(objective.y.as_slice(), &[1.0, 0.0]);
+        assert_eq!(objective.feature_names.unwrap(), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn from_dataframe_validates_like_the_builder() {
+        let df = df!("y" => [1.0, 2.0], "a" => [2.0, f64::INFINITY]).unwrap();
+        assert!(Objective::from_dataframe(&df, "y", &["a"]).is_err());
+        let df = df!("y" => [1.0, 0.0], "a" => [2.0, f64::INFINITY]).unwrap();
+        assert!(Objective::from_dataframe(&df, "y", &["a"]).is_err());
+    }
+
+    #[test]
+    fn from_dataframe_names_the_offending_column() {
+        let df = df!(
+            "y" => [1.0, 0.0],
+            "a" => [2.0, 3.0],
+            "s" => ["u", "v"],
+            "m" => [Some(1.0), None]
+        )
+        .unwrap();
+        let err = Objective::from_dataframe(&df, "y", &["z"]).unwrap_err();
+        assert_eq!(err.to_string(), "Column 'z' not found");
+        let err = Objective::from_dataframe(&df, "y", &["a", "s"]).unwrap_err();
+        assert_eq!(err.to_string(), "Column 's' is not numeric (str)");
+        let err = Objective::from_dataframe(&df, "y", &["m"]).unwrap_err();
+        assert_eq!(err.to_string(), "Column 'm' has missing values");
+        let err = Objective::from_dataframe(&df, "w", &["a"]).unwrap_err();
+        assert_eq!(err.to_string(), "Column 'w' not found");
+    }
+}
@@ -0,0 +1,691 @@
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+use rand::seq::SliceRandom;
+
+use crate::configurations::Cfg;
+use crate::models::Objective;
+
+///
+/// Preprocessing of the predictors. The target and the intercept (last column
+/// of x) are never touched.
+///
+impl Objective {
+    ///
+    /// `(mean, std)` of each predictor, intercept excluded. The std is the
+    /// population one (divides by n).
+    ///
+    pub fn column_stats(&self) -> Vec<(f64, f64)> {
+        let n = self.x.nrows() as f64;
+        self.x
+            .columns(0, self.predictor_count())
+            .column_iter()
+            .map(|col| {
+                let mean = col.mean();
+                let var = col.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+                (mean, var.sqrt())
+            })
+            .collect()
+    }
+
+    ///
+    /// z-score every predictor and return the `(mean, std)` applied to each, so
+    /// `x = z * std + mean` inverts it. Constant columns are left alone and
+    /// reported as `(0.0, 1.0)`, the identity.
+    ///
+    pub fn standardize_in_place(&mut self) -> Vec<(f64, f64)> {
+        let stats: Vec<(f64, f64)> = self
+            .column_stats()
+            .into_iter()
+            .enumerate()
+            .map(|(j, stat)| {
+                if is_constant(self.x.column(j).iter()) {
+                    (0.0, 1.0)
+                } else {
+                    stat
+                }
+            })
+            .collect();
+
+        for (j, (mean, std)) in stats.iter().enumerate() {
+            self.x.column_mut(j).apply(|v| *v = (*v - mean) / std);
+        }
+        stats
+    }
+
+    ///
+    /// Subtract each predictor's mean, keeping its units, and return the means.
+    /// A model fitted on the centered predictors scores the originals the same
+    /// once its intercept is lowered by `means . coefficients`.
+    ///
+    pub fn center(&mut self) -> DVector<f64> {
+        let p = self.predictor_count();
+        let means = DVector::from_fn(p, |j, _| self.x.column(j).mean());
+        for (j, mean) in means.iter().enumerate() {
+            self.x.column_mut(j).add_scalar_mut(-mean);
+        }
+        means
+    }
+
+    ///
+    /// Remove the predictors that never vary, which carry no information and
+    /// leave the Hessian singular, and return their indices. The intercept
+    /// always stays; names follow the columns.
+    ///
+    pub fn drop_constant_columns(&mut self) -> Vec<usize> {
+        let dropped: Vec<usize> = (0..self.predictor_count())
+            .filter(|&j| is_constant(self.x.column(j).iter()))
+            .collect();
+        if dropped.is_empty() {
+            return dropped;
+        }
+        // dropped only holds predictors, so the intercept is kept
+        let keep: Vec<usize> = (0..self.feature_count())
+            .filter(|j| !dropped.contains(j))
+            .collect();
+        self.x = self.x.select_columns(&keep);
+        if let Some(names) = &self.feature_names {
+            let names = keep[..keep.len() - 1].iter().map(|&j| names[j].clone());
+            self.feature_names = Some(names.collect());
+        }
+        dropped
+    }
+
+    ///
+    /// A copy with derived predictors appended after the originals: the powers
+    /// `2..=degree` of each predictor, then (with `interactions`) the product of
+    /// every pair. The intercept stays last and the target is unchanged. Names,
+    /// when present, are derived too (`x1^2`, `x1*x2`).
+    ///
+    /// The width grows quickly: `p` predictors become
+    /// `p * degree + p * (p - 1) / 2` with interactions, so 100 predictors at
+    /// degree 2 already make 5,150 predictors.
+    ///
+    pub fn expand_features(&self, degree: usize, interactions: bool) -> Objective {
+        let p = self.predictor_count();
+        let mut columns: Vec<DVector<f64>> = (0..p).map(|j| self.x.column(j).into()).collect();
+        let mut names: Vec<String> = (0..p).map(|j| self.feature_name(j)).collect();
+
+        for power in 2..=degree {
+            for j in 0..p {
+                columns.push(self.x.column(j).map(|v| v.powi(power as i32)));
+                names.push(format!("{}^{}", self.feature_name(j), power));
+            }
+        }
+        if interactions {
+            for a in 0..p {
+                for b in a + 1..p {
+                    columns.push(self.x.column(a).component_mul(&self.x.column(b)));
+                    names.push(format!("{}*{}", self.feature_name(a), self.feature_name(b)));
+                }
+            }
+        }
+        columns.push(self.x.column(p).into());
+
+        let mut objective = Objective::new(DMatrix::from_columns(&columns), self.y.clone());
+        objective.feature_names = self.feature_names.as_ref().map(|_| names);
+        objective.label_map = self.label_map;
+        objective.weights = self.weights.clone();
+        objective.offset = self.offset.clone();
+        objective
+    }
+
+    ///
+    /// A copy where each of the integer coded predictors `cols` is replaced, in
+    /// place, by `k - 1` dummy columns for its `k` levels; the smallest level is
+    /// the reference and is all zeros. Dummies are named `name=level` (unnamed
+    /// predictors use `x{j}`), so the copy always carries feature names.
+    ///
+    pub fn one_hot(&self, cols: &[usize]) -> Result<Objective> {
+        let p = self.predictor_count();
+        if let Some(j) = cols.iter().find(|&&j| j >= p) {
+            return Err(eyre!(
+                "Column {} is out of range for {} predictors (the intercept can't be encoded)",
+                j,
+                p
+            ));
+        }
+
+        let mut columns: Vec<DVector<f64>> = Vec::with_capacity(p + 1);
+        let mut names: Vec<String> = Vec::with_capacity(p);
+        for j in 0..p {
+            let column: DVector<f64> = self.x.column(j).into();
+            if !cols.contains(&j) {
+                columns.push(column);
+                names.push(self.feature_name(j));
+                continue;
+            }
+            for level in levels(&column, &self.feature_name(j))?.into_iter().skip(1) {
+                columns.push(column.map(|v| if v == level { 1.0 } else { 0.0 }));
+                names.push(format!("{}={}", self.feature_name(j), level));
+            }
+        }
+        columns.push(self.x.column(p).into());
+
+        let mut objective = Objective::new(DMatrix::from_columns(&columns), self.y.clone());
+        objective.feature_names = Some(names);
+        objective.label_map = self.label_map;
+        objective.weights = self.weights.clone();
+        objective.offset = self.offset.clone();
+        Ok(objective)
+    }
+
+    ///
+    /// A copy with only the predictors `keep`, in that order, then the intercept;
+    /// names, weights and the label map carry over. The intercept is always kept,
+    /// so its index is rejected like any other out of range column.
+    ///
+    pub fn subset_features(&self, keep: &[usize]) -> Result<Objective> {
+        let p = self.predictor_count();
+        if let Some(j) = keep.iter().find(|&&j| j >= p) {
+            return Err(eyre!(
+                "Column {} is out of range for {} predictors (the intercept is kept anyway)",
+                j,
+                p
+            ));
+        }
+        if let Some(j) = keep
+            .iter()
+            .enumerate()
+            .find_map(|(i, j)| keep[..i].contains(j).then_some(j))
+        {
+            return Err(eyre!("Column {} is listed twice", j));
+        }
+
+        let mut columns: Vec<DVector<f64>> =
+            keep.iter().map(|&j| self.x.column(j).into()).collect();
+        columns.push(self.x.column(p).into());
+        let mut objective = Objective::new(DMatrix::from_columns(&columns), self.y.clone());
+        objective.feature_names = self
+            .feature_names
+            .as_ref()
+            .map(|names| keep.iter().map(|&j| names[j].clone()).collect());
+        objective.label_map = self.label_map;
+        objective.weights = self.weights.clone();
+        objective.offset = self.offset.clone();
+        Ok(objective)
+    }
+
+    /// `subset_features` by feature name; requires `feature_names`
+    pub fn subset_features_by_name(&self, keep: &[&str]) -> Result<Objective> {
+        let names = self
+            .feature_names
+            .as_ref()
+            .ok_or_else(|| eyre!("The objective has no feature names to select by"))?;
+        let keep = keep
+            .iter()
+            .map(|name| {
+                names
+                    .iter()
+                    .position(|n| n == name)
+                    .ok_or_else(|| eyre!("No feature named '{}'", name))
+            })
+            .collect::<Result<Vec<usize>>>()?;
+        self.subset_features(&keep)
+    }
+
+    ///
+    /// `(train, test)` with `test_fraction` of each class shuffled into test, so
+    /// both splits keep the overall base rate (to within one record per class).
+    /// A rare class is never left out of either split when it has 2+ records.
+    /// Rows stay in their original order within each split. The shuffle follows
+    /// `cfg`'s seed, so the same seed always gives the same partition.
+    ///
+    pub fn stratified_split(
+        &self,
+        test_fraction: f64,
+        cfg: &Cfg,
+    ) -> Result<(Objective, Objective)> {
+        if !(test_fraction > 0.0 && test_fraction < 1.0) {
+            return Err(eyre!(
+                "The test fraction must be in (0, 1); got {}",
+                test_fraction
+            ));
+        }
+        let mut rng = cfg.rng();
+        let mut train = Vec::with_capacity(self.y.len());
+        let mut test = Vec::new();
+        for class in [0.0, 1.0] {
+            let mut rows: Vec<usize> = (0..self.y.len()).filter(|&i| self.y[i] == class).collect();
+            rows.shuffle(&mut rng);
+            let mut n_test = (rows.len() as f64 * test_fraction).round() as usize;
+            if rows.len() >= 2 {
+                n_test = n_test.clamp(1, rows.len() - 1);
+            }
+            test.extend_from_slice(&rows[..n_test]);
+            train.extend_from_slice(&rows[n_test..]);
+        }
+        if train.is_empty() || test.is_empty() {
+            return Err(eyre!(
+                "Too few records ({}) to split at a test fraction of {}",
+                self.y.len(),
+                test_fraction
+            ));
+        }
+        train.sort_unstable();
+        test.sort_unstable();
+        Ok((self.subset(&train), self.subset(&test)))
+    }
+
+    ///
+    /// The records of `self` then those of `other`, which must have the same
+    /// columns. Names and label maps must agree where both sides have one. When
+    /// only one side is weighted (or offset) the other's records weigh 1 (have
+    /// an offset of 0).
+    ///
+    pub fn concat(&self, other: &Objective) -> Result<Objective> {
+        if self.feature_count() != other.feature_count() {
+            return Err(eyre!(
+                "Can't stack {} columns on {} (intercept included)",
+                other.feature_count(),
+                self.feature_count()
+            ));
+        }
+        let feature_names = agree("feature names", &self.feature_names, &other.feature_names)?;
+        let label_map = agree("label maps", &self.label_map, &other.label_map)?;
+
+        let (n, m) = (self.nrows(), other.nrows());
+        let mut x = self.x.clone().resize_vertically(n + m, 0.0);
+        x.rows_mut(n, m).copy_from(&other.x);
+        let y = DVector::from_iterator(n + m, self.y.iter().chain(other.y.iter()).copied());
+        let stack = |a: &Option<DVector<f64>>, b: &Option<DVector<f64>>, fill: f64| {
+            (a.is_some() || b.is_some()).then(|| {
+                let a = a.clone().unwrap_or_else(|| DVector::from_element(n, fill));
+                let b = b.clone().unwrap_or_else(|| DVector::from_element(m, fill));
+                DVector::from_iterator(n + m, a.iter().chain(b.iter()).copied())
+            })
+        };
+
+        let mut objective = Objective::new(x, y);
+        objective.feature_names = feature_names;
+        objective.label_map = label_map;
+        objective.weights = stack(&self.weights, &other.weights, 1.0);
+        objective.offset = stack(&self.offset, &other.offset, 0.0);
+        Ok(objective)
+    }
+
+    /// A copy with only `rows`, keeping names, the label map, their weights and offsets
+    pub(crate) fn subset(&self, rows: &[usize]) -> Objective {
+        let mut objective = Objective::new(self.x.select_rows(rows), self.y.select_rows(rows));
+        objective.feature_names = self.feature_names.clone();
+        objective.label_map = self.label_map;
+        objective.weights = self.weights.as_ref().map(|w| w.select_rows(rows));
+        objective.offset = self.offset.as_ref().map(|o| o.select_rows(rows));
+        objective
+    }
+}
+
+/// Either side's value when the other has none or the same one
+fn agree<T: Clone + PartialEq + std::fmt::Debug>(
+    what: &str,
+    a: &Option<T>,
+    b: &Option<T>,
+) -> Result<Option<T>> {
+    match (a, b) {
+        (Some(a), Some(b)) if a != b => Err(eyre!("The {} differ: {:?} and {:?}", what, a, b)),
+        _ => Ok(a.clone().or_else(|| b.clone())),
+    }
+}
+
+/// Every value equals the first. Exact where a zero std is not: the mean of a
+/// column of 0.1s rounds, leaving a tiny nonzero std.
+pub(crate) fn is_constant<'a>(mut values: impl Iterator<Item = &'a f64>) -> bool {
+    match values.next() {
+        Some(first) => values.all(|v| v == first),
+        None => true,
+    }
+}
+
+/// More levels than this is more likely a continuous column than a category
+const MAX_ONE_HOT_LEVELS: usize = 50;
+
+/// The sorted distinct levels of an integer coded column
+fn levels(column: &DVector<f64>, name: &str) -> Result<Vec<f64>> {
+    if let Some(v) = column.iter().find(|v| v.fract() != 0.0) {
+        return Err(eyre!(
+            "Column '{}' holds {}; one-hot encoding expects integer codes",
+            name,
+            v
+        ));
+    }
+    let mut levels: Vec<f64> = column.iter().copied().collect();
+    levels.sort_by(f64::total_cmp);
+    levels.dedup();
+    if levels.len() > MAX_ONE_HOT_LEVELS {
+        return Err(eyre!(
+            "Column '{}' has {} levels; one-hot encoding is capped at {}",
+            name,
+            levels.len(),
+            MAX_ONE_HOT_LEVELS
+        ));
+    }
+    Ok(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::tests::toy;
+
+    #[test]
+    fn column_stats_are_the_population_mean_and_std() {
+        let stats = toy().column_stats();
+        assert_eq!(stats.len(), 1);
+        let (mean, std) = stats[0];
+        assert!((mean + 0.125).abs() < 1e-12);
+        // var(i) over 0..20 is 399 / 12, scaled by 1 / 16
+        assert!((std - (399.0f64 / 12.0 / 16.0).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn standardize_in_place_z_scores_the_predictors_only() {
+        let mut objective = toy();
+        let before = objective.x.clone();
+        let stats = objective.standardize_in_place();
+        assert_eq!(stats, toy().column_stats());
+
+        let (mean, std) = objective.column_stats()[0];
+        assert!(mean.abs() < 1e-12);
+        assert!((std - 1.0).abs() < 1e-12);
+        assert!(objective.x.column(1).iter().all(|v| *v == 1.0));
+
+        let (m, s) = stats[0];
+        let restored = objective.x.column(0).map(|z| z * s + m);
+        assert!((restored - before.column(0)).amax() < 1e-12);
+    }
+
+    #[test]
+    fn standardize_in_place_leaves_constant_columns_alone() {
+        let x = DMatrix::from_row_slice(3, 3, &[1.0, 4.0, 1.0, 2.0, 4.0, 1.0, 3.0, 4.0, 1.0]);
+        let mut objective = Objective::new(x, DVector::from_row_slice(&[0.0, 1.0, 0.0]));
+        let stats = objective.standardize_in_place();
+        assert_eq!(stats[1], (0.0, 1.0));
+        assert!(objective.x.column(1).iter().all(|v| *v == 4.0));
+
+        // a column of 0.1s has a tiny nonzero std from rounding
+        let x = DMatrix::from_row_slice(3, 2, &[0.1, 1.0, 0.1, 1.0, 0.1, 1.0]);
+        let mut objective = Objective::new(x, DVector::from_row_slice(&[0.0, 1.0, 0.0]));
+        assert!(objective.column_stats()[0].1 > 0.0);
+        assert_eq!(objective.standardize_in_place(), [(0.0, 1.0)]);
+        assert!(objective.x.column(0).iter().all(|v| *v == 0.1));
+    }
+
+    /// Two named predictors (1, 2, 3) and (4, 5, 6), then the intercept
+    fn two_named() -> Objective {
+        let x = DMatrix::from_row_slice(3, 3, &[1.0, 4.0, 1.0, 2.0, 5.0, 1.0, 3.0, 6.0, 1.0]);
+        Objective::new(x, DVector::from_row_slice(&[0.0, 1.0, 1.0]))
+            .with_feature_names(vec!["a".into(), "b".into()])
+            .unwrap()
+    }
+
+    #[test]
+    fn expand_features_appends_powers_then_products() {
+        let expanded = two_named().expand_features(3, true);
+        // 2 * 3 + 1 predictors, then the intercept
+        assert_eq!(expanded.feature_count(), 8);
+        assert_eq!(
+            expanded.feature_names.as_deref().unwrap(),
+            ["a", "b", "a^2", "b^2", "a^3", "b^3", "a*b"]
+        );
+        assert_eq!(
+            expanded.x.row(2).iter().copied().collect::<Vec<_>>(),
+            [3.0, 6.0, 9.0, 36.0, 27.0, 216.0, 18.0, 1.0]
+        );
+        assert_eq!(expanded.y, two_named().y);
+    }
+
+    #[test]
+    fn expand_features_at_degree_one_without_interactions_is_a_copy() {
+        let original = toy();
+        let expanded = original.expand_features(1, false);
+        assert_eq!(expanded.x, original.x);
+        assert!(expanded.feature_names.is_none());
+    }
+
+    #[test]
+    fn one_hot_replaces_a_coded_column_by_its_dummies() {
+        let x = DMatrix::from_row_slice(
+            4,
+            3,
+            &[2.0, 0.5, 1.0, 0.0, 0.6, 1.0, 1.0, 0.7, 1.0, 2.0, 0.8, 1.0],
+        );
+        let objective = Objective::new(x, DVector::from_row_slice(&[0.0, 1.0, 0.0, 1.0]));
+        let encoded = objective.one_hot(&[0]).unwrap();
+
+        // levels 0, 1, 2: level 0 is the (all zero) reference
+        assert_eq!(
+            encoded.feature_names.as_deref().unwrap(),
+            ["x0=1", "x0=2", "x1"]
+        );
+        assert_eq!(
+            encoded.x,
+            DMatrix::from_row_slice(
+                4,
+                4,
+                &[0.0, 1.0, 0.5, 1.0, 0.0, 0.0, 0.6, 1.0, 1.0, 0.0, 0.7, 1.0, 0.0, 1.0, 0.8, 1.0],
+            )
+        );
+    }
+
+    #[test]
+    fn one_hot_rejects_the_intercept_and_non_integer_codes() {
+        let err = toy().one_hot(&[1]).unwrap_err().to_string();
+        assert!(
+            err.contains("Column 1 is out of range for 1 predictors"),
+            "{err}"
+        );
+        let err = toy().one_hot(&[0]).unwrap_err().to_string();
+        assert!(err.contains("holds -2.5;"), "{err}");
+    }
+
+    #[test]
+    fn one_hot_caps_the_number_of_levels() {
+        let x = DMatrix::from_fn(60, 2, |i, j| if j == 0 { i as f64 } else { 1.0 });
+        let objective = Objective::new(x, DVector::from_fn(60, |i, _| (i % 2) as f64));
+        let err = objective.one_hot(&[0]).unwrap_err().to_string();
+        assert!(err.contains("has 60 levels"), "{err}");
+    }
+
+    #[test]
+    fn stratified_split_keeps_the_base_rate_in_both_splits() {
+        let objective = toy();
+        let (train, test) = objective.stratified_split(0.25, &seeded(7)).unwrap();
+        // 2.5 of each class's 10 records rounds to 3
+        assert_eq!(test.class_counts(), (3, 3));
+        assert_eq!(train.class_counts(), (7, 7));
+
+        // a partition of the records, each split in the original order
+        let fold = |o: &Objective| o.x.column(0).iter().copied().collect::<Vec<f64>>();
+        let (a, b) = (fold(&train), fold(&test));
+        assert!(a.windows(2).all(|w| w[0] < w[1]));
+        assert!(b.windows(2).all(|w| w[0] < w[1]));
+        let mut all = [a, b].concat();
+        all.sort_by(f64::total_cmp);
+        assert_eq!(all, fold(&objective));
+    }
+
+    fn seeded(seed: u64) -> Cfg {
+        CfgBuilder::new().seed(seed).build()
+    }
+
+    #[test]
+    fn stratified_split_is_seeded() {
+        let objective = toy();
+        let (train, test) = objective.stratified_split(0.3, &seeded(11)).unwrap();
+        let (train_again, test_again) = objective.stratified_split(0.3, &seeded(11)).unwrap();
+        assert_eq!(train.x, train_again.x);
+        assert_eq!(test.x, test_again.x);
+        let differs = (0..20u64).any(|seed| {
+            let (_, other) = objective.stratified_split(0.3, &seeded(seed)).unwrap();
+            other.x != test.x
+        });
+        assert!(differs);
+    }
+
+    #[test]
+    fn stratified_split_keeps_a_rare_class_in_both_splits() {
+        let mut objective = toy();
+        objective.y = DVector::from_fn(20, |i, _| if i < 2 { 1.0 } else { 0.0 });
+        let (train, test) = objective.stratified_split(0.1, &seeded(3)).unwrap();
+        assert_eq!(train.class_counts().1, 1);
+        assert_eq!(test.class_counts().1, 1);
+
+        let err = objective.stratified_split(1.0, &seeded(3)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The test fraction must be in (0, 1); got 1"
+        );
+    }
+
+    #[test]
+    fn subset_features_keeps_the_listed_predictors_in_order() {
+        let subset = two_named().subset_features(&[1]).unwrap();
+        assert_eq!(
+            subset.x,
+            DMatrix::from_row_slice(3, 2, &[4.0, 1.0, 5.0, 1.0, 6.0, 1.0])
+        );
+        assert_eq!(subset.feature_names.as_deref().unwrap(), ["b"]);
+
+        let swapped = two_named().subset_features_by_name(&["b", "a"]).unwrap();
+        assert_eq!(
+            swapped.x.row(0).iter().copied().collect::<Vec<_>>(),
+            [4.0, 1.0, 1.0]
+        );
+        assert_eq!(swapped.feature_names.as_deref().unwrap(), ["b", "a"]);
+    }
+
+    #[test]
+    fn subset_features_fits_as_a_csv_of_only_those_columns() {
+        let base = toy();
+        let row = |i: usize, cols: &[usize]| {
+            let values = [
+                base.x[(i, 0)],
+                ((i * 7) % 5) as f64 / 2.0,
+                ((i * 3) % 4) as f64 - 1.5,
+            ];
+            let fields: Vec<String> = cols.iter().map(|&j| values[j].to_string()).collect();
+            format!("{},{}\n", base.y[i], fields.join(","))
+        };
+        let write = |name: &str, header: &str, cols: &[usize]| {
+            let contents: String = std::iter::once(format!("{}\n", header))
+                .chain((0..20).map(|i| row(i, cols)))
+                .collect();
+            let path = std::env::temp_dir().join(format!(
+                "propensity-{}-{}.csv",
+                name,
+                std::process::id()
+            ));
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+        let full = Objective::from_csv(write("subset-full", "y,a,b,c", &[0, 1, 2]), true).unwrap();
+        let reduced = Objective::from_csv(write("subset-reduced", "y,c,a", &[2, 0]), true).unwrap();
+
+        let subset = full.subset_features(&[2, 0]).unwrap();
+        assert_eq!(subset.x, reduced.x);
+        let cfg = || CfgBuilder::new().gtol(1e-10).build();
+        let (a, b) = (subset.fit(cfg()).unwrap(), reduced.fit(cfg()).unwrap());
+        assert_eq!(a.all_betas, b.all_betas);
+    }
+
+    #[test]
+    fn subset_features_rejects_bad_selections() {
+        let objective = two_named();
+        let err = objective.subset_features(&[2]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Column 2 is out of range for 2 predictors (the intercept is kept anyway)"
+        );
+        let err = objective.subset_features(&[0, 1, 0]).unwrap_err();
+        assert_eq!(err.to_string(), "Column 0 is listed twice");
+        let err = objective.subset_features_by_name(&["c"]).unwrap_err();
+        assert_eq!(err.to_string(), "No feature named 'c'");
+        let err = toy().subset_features_by_name(&["x0"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The objective has no feature names to select by"
+        );
+    }
+
+    #[test]
+    fn concat_stacks_the_records_and_fills_missing_weights() {
+        let top = two_named().subset(&[0, 1]);
+        let mut bottom = two_named().subset(&[2]);
+        bottom.weights = Some(DVector::from_vec(vec![3.0]));
+        let stacked = top.concat(&bottom).unwrap();
+        assert_eq!(stacked.x, two_named().x);
+        assert_eq!(stacked.y, two_named().y);
+        assert_eq!(stacked.feature_names, two_named().feature_names);
+        assert_eq!(stacked.weights.unwrap().as_slice(), &[1.0, 1.0, 3.0]);
+        assert!(stacked.offset.is_none());
+
+        let mut offset = toy().subset(&[0]);
+        offset.offset = Some(DVector::from_vec(vec![0.5]));
+        let stacked = toy().subset(&[1, 2]).concat(&offset).unwrap();
+        assert_eq!(stacked.offset.unwrap().as_slice(), &[0.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn concat_rejects_mismatched_columns_and_names() {
+        let err = toy().concat(&two_named()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't stack 3 columns on 2 (intercept included)"
+        );
+        let renamed = two_named()
+            .subset(&[0])
+            .with_feature_names(vec!["a".into(), "c".into()])
+            .unwrap();
+        let err = two_named().concat(&renamed).unwrap_err().to_string();
+        assert!(err.starts_with("The feature names differ"), "{err}");
+        // an unnamed side takes the other's names
+        let unnamed = Objective::new(two_named().x, two_named().y);
+        let stacked = unnamed.concat(&two_named()).unwrap();
+        assert_eq!(stacked.nrows(), 6);
+        assert_eq!(stacked.feature_names, two_named().feature_names);
+    }
+
+    #[test]
+    fn center_removes_the_means_and_keeps_the_fit_up_to_the_intercept() {
+        let objective = toy();
+        let mut centered = toy();
+        let means = centered.center();
+        assert_eq!(means.as_slice(), &[-0.125]);
+        assert!(centered.x.column(0).mean().abs() < 1e-12);
+        assert!(centered.x.column(1).iter().all(|v| *v == 1.0));
+        assert_eq!(centered.column_stats()[0].1, objective.column_stats()[0].1);
+
+        let cfg = CfgBuilder::new().gtol(1e-10).build();
+        let original = objective.fit(cfg.clone()).unwrap();
+        let shifted = centered.fit(cfg).unwrap();
+        assert!((shifted.coefficients[0] - original.coefficients[0]).abs() < 1e-6);
+        let intercept = shifted.intercept - means.dot(&shifted.coefficients);
+        assert!((intercept - original.intercept).abs() < 1e-6);
+    }
+
+    #[test]
+    fn drop_constant_columns_removes_them_with_their_names() {
+        let x = DMatrix::from_row_slice(
+            3,
+            5,
+            &[
+                7.0, 1.0, 2.0, 0.1, 1.0, 7.0, 2.0, 2.0, 0.1, 1.0, 7.0, 3.0, 2.0, 0.1, 1.0,
+            ],
+        );
+        let mut objective = Objective::new(x, DVector::from_vec(vec![0.0, 1.0, 1.0]))
+            .with_feature_names(vec!["a".into(), "b".into(), "c".into(), "d".into()])
+            .unwrap();
+        // rounding leaves the 0.1s a nonzero std
+        assert!(objective.column_stats()[3].1 > 0.0);
+        assert_eq!(objective.drop_constant_columns(), [0, 2, 3]);
+        assert_eq!(
+            objective.x,
+            DMatrix::from_row_slice(3, 2, &[1.0, 1.0, 2.0, 1.0, 3.0, 1.0])
+        );
+        assert_eq!(objective.feature_names.as_deref().unwrap(), ["b"]);
+
+        let mut varied = toy();
+        assert!(varied.drop_constant_columns().is_empty());
+        assert_eq!(varied.x, toy().x);
+    }
+}
@@ -0,0 +1,280 @@
+use argmin::core::{CostFunction, Error, Gradient};
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+
+use std::cell::Cell;
+
+use crate::configurations::Cfg;
+use crate::logit::Design;
+use crate::models::Objective;
+
+///
+/// `objective` plus the ridge penalty `l2 / 2 * |w|²` over every slot but the
+/// intercept (the last), so the solvers fit the penalized cost unchanged.
+///
+/// The objective is type erased: a `Ridge` generic over the design would let
+/// the trait solver chase `Ridge<Ridge<..>>` while proving `&D: CostFunction`.
+///
+pub(crate) struct Ridge<'a> {
+    inner: Box<dyn Penalizable + 'a>,
+    l2: f64,
+}
+
+impl<'a> Ridge<'a> {
+    pub(crate) fn new<D>(inner: &'a D, l2: f64) -> Result<Self>
+    where
+        D: Design,
+        &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+            + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+    {
+        if !(l2.is_finite() && l2 >= 0.0) {
+            return Err(eyre!("The l2 penalty must be finite and >= 0; got {}", l2));
+        }
+        Ok(Ridge {
+            inner: Box::new(inner),
+            l2,
+        })
+    }
+    /// `l2 * w` with the intercept slot zeroed
+    fn penalty_gradient(&self, param: &DVector<f64>) -> DVector<f64> {
+        let mut gradient = param * self.l2;
+        gradient[param.len() - 1] = 0.0;
+        gradient
+    }
+}
+
+/// What `Ridge` needs of the objective it wraps
+trait Penalizable {
+    fn design(&self) -> &dyn Design;
+    fn cost(&self, param: &DVector<f64>) -> Result<f64, Error>;
+    fn gradient(&self, param: &DVector<f64>) -> Result<DVector<f64>, Error>;
+}
+
+impl<'a, D> Penalizable for &'a D
+where
+    D: Design,
+    &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+        + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+{
+    fn design(&self) -> &dyn Design {
+        *self
+    }
+    fn cost(&self, param: &DVector<f64>) -> Result<f64, Error> {
+        CostFunction::cost(self, param)
+    }
+    fn gradient(&self, param: &DVector<f64>) -> Result<DVector<f64>, Error> {
+        Gradient::gradient(self, param)
+    }
+}
+
+impl Design for Ridge<'_> {
+    fn feature_count(&self) -> usize {
+        self.inner.design().feature_count()
+    }
+    fn record_count(&self) -> usize {
+        self.inner.design().record_count()
+    }
+    fn param_len(&self) -> usize {
+        self.inner.design().param_len()
+    }
+    fn hessian(&self, param: &DVector<f64>) -> Result<DMatrix<f64>> {
+        let mut hessian = self.inner.design().hessian(param)?;
+        for j in 0..param.len() - 1 {
+            hessian[(j, j)] += self.l2;
+        }
+        Ok(hessian)
+    }
+    fn linear_predictor(&self, param: &DVector<f64>) -> Option<DVector<f64>> {
+        self.inner.design().linear_predictor(param)
+    }
+    /// the batch's share of the penalty, so an epoch of batches sums to the full gradient
+    fn batch_gradient(&self, param: &DVector<f64>, rows: &[usize]) -> Result<DVector<f64>> {
+        let share = rows.len() as f64 / self.record_count() as f64;
+        Ok(self.inner.design().batch_gradient(param, rows)? + self.penalty_gradient(param) * share)
+    }
+}
+
+impl CostFunction for &Ridge<'_> {
+    type Param = DVector<f64>;
+    type Output = f64;
+
+    fn cost(&self, param: &Self::Param) -> Result<Self::Output, Error> {
+        let slopes = param.rows(0, param.len() - 1);
+        Ok(self.inner.cost(param)? + self.l2 / 2.0 * slopes.norm_squared())
+    }
+}
+
+impl Gradient for &Ridge<'_> {
+    type Param = DVector<f64>;
+    type Gradient = DVector<f64>;
+
+    fn gradient(&self, param: &Self::Param) -> Result<Self::Gradient, Error> {
+        Ok(self.inner.gradient(param)? + self.penalty_gradient(param))
+    }
+}
+
+/// The intercept search stops once a step moves it by less than this, relatively
+const INTERCEPT_TOLERANCE: f64 = 1e-13;
+/// Doublings of the bracket before the intercept is taken to diverge
+const INTERCEPT_MAX_EXPANSIONS: usize = 64;
+const INTERCEPT_MAX_ITERS: usize = 200;
+
+///
+/// `inner` over its slopes alone, with the intercept (the last slot) solved for
+/// at every evaluation. OWL-QN penalizes every slot it searches, so searching
+/// the slopes only leaves the intercept out of the l1 penalty, as `Ridge`
+/// leaves it out of the l2 one. At the solved intercept its score is zero, so
+/// the gradient over the slopes is the inner one's (the envelope theorem).
+///
+pub(crate) struct ProfiledIntercept<'a> {
+    inner: Box<dyn Penalizable + 'a>,
+    /// where every intercept search starts; fixed, so that the cost is a
+    /// function of the slopes alone, as the line search expects
+    start: Cell<f64>,
+}
+
+impl<'a> ProfiledIntercept<'a> {
+    pub(crate) fn new<D>(inner: &'a D) -> Self
+    where
+        D: Design,
+        &'a D: CostFunction<Param = DVector<f64>, Output = f64>
+            + Gradient<Param = DVector<f64>, Gradient = DVector<f64>>,
+    {
+        ProfiledIntercept {
+            inner: Box::new(inner),
+            start: Cell::new(0.0),
+        }
+    }
+    /// Start the intercept search at the intercept of `param`, a full param
+    pub(crate) fn start_from(&self, param: &DVector<f64>) {
+        self.start.set(param[param.len() - 1]);
+    }
+    ///
+    /// `slopes` with the intercept minimizing the inner cost at them appended.
+    /// The cost is convex, so its derivative in the intercept is increasing:
+    /// the root is bracketed by doubling steps, then found by regula falsi
+    /// (the Illinois variant).
+    ///
+    pub(crate) fn full(&self, slopes: &DVector<f64>) -> Result<DVector<f64>, Error> {
+        let last = slopes.len();
+        let mut param = slopes.clone().insert_row(last, 0.0);
+        let mut score = |intercept: f64| -> Result<f64, Error> {
+            param[last] = intercept;
+            Ok(self.inner.gradient(&param)?[last])
+        };
+
+        let mut a = self.start.get();
+        let mut fa = score(a)?;
+        let mut step = 1.0;
+        let mut b = a - fa.signum() * step;
+        let mut fb = score(b)?;
+        let mut expansions = 0;
+        while fa != 0.0 && fb != 0.0 && fa.signum() == fb.signum() {
+            if expansions == INTERCEPT_MAX_EXPANSIONS {
+                return Err(Error::msg(
+                    "The intercept diverges; the l1 fit needs both classes",
+                ));
+            }
+            (a, fa) = (b, fb);
+            step *= 2.0;
+            b = a - fa.signum() * step;
+            fb = score(b)?;
+            expansions += 1;
+        }
+
+        let mut intercept = if fa == 0.0 { a } else { b };
+        if fa != 0.0 && fb != 0.0 {
+            for _ in 0..INTERCEPT_MAX_ITERS {
+                let c = b - fb * (b - a) / (fb - fa);
+                let fc = score(c)?;
+                let moved = (c - b).abs();
+                if fc.signum() != fb.signum() {
+                    (a, fa) = (b, fb);
+                } else {
+                    fa /= 2.0;
+                }
+                (b, fb) = (c, fc);
+                intercept = c;
+                if fc == 0.0 || moved <= INTERCEPT_TOLERANCE * (1.0 + c.abs()) {
+                    break;
+                }
+            }
+        }
+        param[last] = intercept;
+        Ok(param)
+    }
+}
+
+impl Design for ProfiledIntercept<'_> {
+    fn feature_count(&self) -> usize {
+        self.inner.design().feature_count() - 1
+    }
+    fn record_count(&self) -> usize {
+        self.inner.design().record_count()
+    }
+    fn param_len(&self) -> usize {
+        self.inner.design().param_len() - 1
+    }
+}
+
+impl CostFunction for &ProfiledIntercept<'_> {
+    type Param = DVector<f64>;
+    type Output = f64;
+
+    fn cost(&self, param: &Self::Param) -> Result<Self::Output, Error> {
+        self.inner.cost(&self.full(param)?)
+    }
+}
+
+impl Gradient for &ProfiledIntercept<'_> {
+    type Param = DVector<f64>;
+    type Gradient = DVector<f64>;
+
+    fn gradient(&self, param: &Self::Param) -> Result<Self::Gradient, Error> {
+        let gradient = self.inner.gradient(&self.full(param)?)?;
+        Ok(gradient.rows(0, param.len()).into_owned())
+    }
+}
+
+///
+/// The coefficients (intercept excluded) at each of `lambdas` as the `l2`
+/// penalty, in the order given. Each fit starts from the previous one's betas,
+/// so sweeping the lambdas in order (either direction) converges fastest.
+///
+pub fn regularization_path(
+    objective: &Objective,
+    lambdas: &[f64],
+    cfg: &Cfg,
+) -> Result<Vec<(f64, DVector<f64>)>> {
+    let mut path = Vec::with_capacity(lambdas.len());
+    let mut warm_start = cfg.warm_start.clone();
+    for &lambda in lambdas {
+        let mut cfg = cfg.clone();
+        cfg.l2 = lambda;
+        cfg.warm_start = warm_start;
+        let findings = objective.fit(cfg)?;
+        path.push((lambda, findings.coefficients.clone()));
+        warm_start = Some(findings.all_betas);
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::tests::toy;
+
+    #[test]
+    fn regularization_path_shrinks_the_coefficients_monotonically() {
+        let objective = toy();
+        let lambdas = [0.0, 0.1, 1.0, 10.0, 100.0];
+        let path = regularization_path(&objective, &lambdas, &CfgBuilder::new().build()).unwrap();
+        assert_eq!(path.iter().map(|(l, _)| *l).collect::<Vec<_>>(), lambdas);
+        let norms: Vec<f64> = path.iter().map(|(_, c)| c.norm()).collect();
+        assert!(norms.windows(2).all(|pair| pair[1] < pair[0]));
+        // unpenalized, the path starts at the plain fit
+        let plain = objective.fit(CfgBuilder::new().build()).unwrap();
+        assert!((path[0].1.clone() - plain.coefficients).amax() < 1e-6);
+    }
+}
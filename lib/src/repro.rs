@@ -0,0 +1,158 @@
+use nalgebra::base::{DMatrix, DVector};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+use crate::models::Findings;
+
+///
+/// Fingerprint of a fit: two runs with equal records used the same data, the
+/// same configuration and the same version of the crate, and arrived at the
+/// same coefficients.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReproRecord {
+    pub crate_version: String,
+    pub data_hash: u64,
+    pub config: String,
+    pub seed: Option<u64>,
+    pub coefficient_hash: u64,
+}
+
+impl fmt::Display for ReproRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version={} data={:016x} seed={} coefficients={:016x} config={}",
+            self.crate_version,
+            self.data_hash,
+            self.seed
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "none".to_string()),
+            self.coefficient_hash,
+            self.config,
+        )
+    }
+}
+
+impl Findings<'_> {
+    pub fn reproducibility_record(&self) -> ReproRecord {
+        let mut data = Fnv1a::new();
+        data.write_matrix(&self.objective.x);
+        data.write_vector(&self.objective.y);
+        data.write_optional(self.objective.weights.as_ref());
+        data.write_optional(self.objective.offset.as_ref());
+
+        let mut coefficients = Fnv1a::new();
+        coefficients.write_vector(&self.all_betas);
+
+        ReproRecord {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            data_hash: data.finish(),
+            config: format!("{:?}", self.cfg),
+            seed: self.cfg.seed,
+            coefficient_hash: coefficients.finish(),
+        }
+    }
+}
+
+///
+/// 64 bit FNV-1a over the little-endian bytes of the values. Unlike the std
+/// `DefaultHasher` the output is fixed across Rust releases and platforms.
+///
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    pub(crate) fn new() -> Self {
+        Fnv1a(Self::OFFSET)
+    }
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+    fn write_values<'v>(&mut self, values: impl Iterator<Item = &'v f64>) {
+        for v in values {
+            self.write(&v.to_le_bytes());
+        }
+    }
+    /// shape first so that reshaped data hashes differently
+    fn write_matrix(&mut self, m: &DMatrix<f64>) {
+        self.write(&(m.nrows() as u64).to_le_bytes());
+        self.write(&(m.ncols() as u64).to_le_bytes());
+        self.write_values(m.iter());
+    }
+    fn write_vector(&mut self, v: &DVector<f64>) {
+        self.write(&(v.len() as u64).to_le_bytes());
+        self.write_values(v.iter());
+    }
+    /// a tag first so that `None` and an empty vector hash differently
+    fn write_optional(&mut self, v: Option<&DVector<f64>>) {
+        match v {
+            None => self.write(&[0]),
+            Some(v) => {
+                self.write(&[1]);
+                self.write_vector(v);
+            }
+        }
+    }
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::tests::toy;
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        let mut hasher = Fnv1a::new();
+        assert_eq!(hasher.finish(), 0xcbf2_9ce4_8422_2325);
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn the_data_hash_covers_the_weights_and_the_offset() {
+        let cfg = || CfgBuilder::new().seed(3).build();
+        let objective = toy();
+        let plain = objective.fit(cfg()).unwrap().reproducibility_record();
+        assert_eq!(
+            plain,
+            objective.fit(cfg()).unwrap().reproducibility_record()
+        );
+
+        let mut weighted = toy();
+        weighted.weights = Some(DVector::from_element(weighted.nrows(), 1.0));
+        let record = weighted.fit(cfg()).unwrap().reproducibility_record();
+        // unit weights fit the same betas, yet the data differs
+        assert_eq!(record.coefficient_hash, plain.coefficient_hash);
+        assert_ne!(record.data_hash, plain.data_hash);
+
+        let offset = toy()
+            .with_offset(DVector::from_element(objective.nrows(), 0.0))
+            .unwrap();
+        let record = offset.fit(cfg()).unwrap().reproducibility_record();
+        assert_ne!(record.data_hash, plain.data_hash);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn the_record_round_trips_through_json() {
+        let record = toy()
+            .fit(CfgBuilder::new().seed(3).build())
+            .unwrap()
+            .reproducibility_record();
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(serde_json::from_str::<ReproRecord>(&json).unwrap(), record);
+    }
+}
@@ -0,0 +1,212 @@
+use argmin::core::{CostFunction, Error, Gradient};
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::{DMatrix, DVector};
+use nalgebra_sparse::CscMatrix;
+
+use crate::logit::{check_param_len, neg_log_likelihood, Design};
+use crate::models::{check_binary, sigmoid_vec, Findings, Objective, Prediction};
+
+///
+/// Objective backed by a compressed sparse column matrix, for designs that are
+/// mostly zeros (e.g. one-hot encoded). As with `Objective`, the target is held
+/// separately and the intercept is the last column of x.
+///
+#[derive(Debug)]
+pub struct SparseObjective {
+    pub x: CscMatrix<f64>,
+    pub y: DVector<f64>,
+    /// per record weights in the likelihood, as `Objective::weights`
+    pub weights: Option<DVector<f64>>,
+    /// a per record term added to `X * w`, as `Objective::offset`
+    pub offset: Option<DVector<f64>>,
+}
+
+impl SparseObjective {
+    pub fn new(x: CscMatrix<f64>, y: DVector<f64>) -> Result<Self> {
+        if x.nrows() != y.len() {
+            return Err(eyre!(
+                "x has {} rows but y has {} values",
+                x.nrows(),
+                y.len()
+            ));
+        }
+        if let Some(v) = x.values().iter().find(|v| !v.is_finite()) {
+            return Err(eyre!("x must hold finite values; found {}", v));
+        }
+        check_binary(&y)?;
+        Ok(SparseObjective {
+            x,
+            y,
+            weights: None,
+            offset: None,
+        })
+    }
+    pub fn feature_count(&self) -> usize {
+        self.x.ncols()
+    }
+    /// `X * w` plus the offset, visiting only the stored entries
+    fn linear_predictor(&self, w: &DVector<f64>) -> DVector<f64> {
+        let mut z = match &self.offset {
+            Some(offset) => offset.clone(),
+            None => DVector::zeros(self.x.nrows()),
+        };
+        for (j, col) in self.x.col_iter().enumerate() {
+            for (&i, v) in col.row_indices().iter().zip(col.values()) {
+                z[i] += v * w[j];
+            }
+        }
+        z
+    }
+    /// `X' * r` visiting only the stored entries
+    fn tr_mul(&self, r: &DVector<f64>) -> DVector<f64> {
+        DVector::from_iterator(
+            self.x.ncols(),
+            self.x.col_iter().map(|col| {
+                col.row_indices()
+                    .iter()
+                    .zip(col.values())
+                    .map(|(&i, v)| v * r[i])
+                    .sum::<f64>()
+            }),
+        )
+    }
+}
+
+/// Drops the explicit zeros of the dense design; the weights and offset carry over
+impl From<&Objective> for SparseObjective {
+    fn from(objective: &Objective) -> Self {
+        SparseObjective {
+            x: CscMatrix::from(&objective.x),
+            y: objective.y.clone(),
+            weights: objective.weights.clone(),
+            offset: objective.offset.clone(),
+        }
+    }
+}
+
+impl Design for SparseObjective {
+    fn feature_count(&self) -> usize {
+        SparseObjective::feature_count(self)
+    }
+    fn record_count(&self) -> usize {
+        self.x.nrows()
+    }
+    fn linear_predictor(&self, param: &DVector<f64>) -> Option<DVector<f64>> {
+        Some(SparseObjective::linear_predictor(self, param))
+    }
+}
+
+impl CostFunction for &SparseObjective {
+    type Param = DVector<f64>;
+    type Output = f64;
+
+    fn cost(&self, param: &Self::Param) -> Result<Self::Output, Error> {
+        check_param_len(self.feature_count(), param)?;
+        Ok(neg_log_likelihood(
+            &self.linear_predictor(param),
+            &self.y,
+            self.weights.as_ref(),
+        ))
+    }
+}
+
+impl Gradient for &SparseObjective {
+    type Param = DVector<f64>;
+    type Gradient = DVector<f64>;
+
+    fn gradient(&self, param: &Self::Param) -> Result<Self::Gradient, Error> {
+        check_param_len(self.feature_count(), param)?;
+        let mut residual = sigmoid_vec(&self.linear_predictor(param)) - &self.y;
+        if let Some(weights) = &self.weights {
+            residual.component_mul_assign(weights);
+        }
+        Ok(self.tr_mul(&residual))
+    }
+}
+
+///
+/// Predictions of a sparse fit, on its own records or on new (dense) ones laid
+/// out as its x. Inference and the report remain specific to the dense
+/// `Objective`.
+///
+impl Findings<'_, SparseObjective> {
+    /// The linear predictor `X * w` plus the offset, on the training records
+    pub fn decision_function(&self) -> Prediction<f64> {
+        Prediction::new(self.objective.linear_predictor(&self.all_betas))
+    }
+    /// Probability of the positive class for each training record
+    pub fn predict_proba(&self) -> Prediction<f64> {
+        Prediction::new(self.cfg.link.inverse_vec(&self.decision_function().inner))
+    }
+    /// `predict_proba`, or 1/0 past 0.5 when `binary`
+    pub fn predict(&self, binary: bool) -> Prediction<f64> {
+        let probabilities = self.predict_proba();
+        match binary {
+            true => probabilities.threshold(0.5),
+            false => probabilities,
+        }
+    }
+    /// `predict_proba` for new records, intercept placeholder in the last column
+    pub fn predict_proba_on(&self, x: &DMatrix<f64>) -> Result<Prediction<f64>> {
+        if x.ncols() != self.all_betas.len() {
+            return Err(eyre!(
+                "Expected {} columns (including the intercept), got {}",
+                self.all_betas.len(),
+                x.ncols()
+            ));
+        }
+        Ok(Prediction::new(
+            self.cfg.link.inverse_vec(&(x * &self.all_betas)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::{run, tests::toy};
+
+    #[test]
+    fn the_sparse_fit_matches_the_dense_one() {
+        let objective = toy();
+        let dense = run(&objective, CfgBuilder::new().build()).unwrap();
+        let sparse_objective = SparseObjective::from(&objective);
+        let sparse = run(&sparse_objective, CfgBuilder::new().build()).unwrap();
+        assert!((sparse.all_betas.clone() - &dense.all_betas).amax() < 1e-8);
+        assert!((sparse.predict_proba().inner - dense.predict_proba().inner).amax() < 1e-8);
+        assert_eq!(sparse.predict(true).inner, dense.predict(true).inner);
+        let on = sparse.predict_proba_on(&objective.x).unwrap();
+        assert!((on.inner - dense.predict_proba().inner).amax() < 1e-8);
+        assert!(sparse
+            .predict_proba_on(&objective.x.columns(0, 1).into_owned())
+            .is_err());
+    }
+
+    #[test]
+    fn the_weights_and_offset_carry_over() {
+        let n = toy().nrows();
+        let weights = DVector::from_fn(n, |i, _| 1.0 + (i % 3) as f64);
+        let offset = DVector::from_fn(n, |i, _| 0.1 * (i % 4) as f64);
+        let mut objective = toy().with_offset(offset).unwrap();
+        objective.weights = Some(weights);
+        let dense = run(&objective, CfgBuilder::new().build()).unwrap();
+        let sparse_objective = SparseObjective::from(&objective);
+        let sparse = run(&sparse_objective, CfgBuilder::new().build()).unwrap();
+        assert!((sparse.all_betas - dense.all_betas).amax() < 1e-8);
+        assert!((sparse.final_nll - dense.final_nll).abs() < 1e-10);
+    }
+
+    #[test]
+    fn new_rejects_a_non_binary_target_and_non_finite_x() {
+        let objective = toy();
+        let x = CscMatrix::from(&objective.x);
+        let mut y = objective.y.clone();
+        y[0] = 2.0;
+        assert!(SparseObjective::new(x.clone(), y).is_err());
+        let mut dense = objective.x.clone();
+        dense[(0, 0)] = f64::INFINITY;
+        assert!(SparseObjective::new(CscMatrix::from(&dense), objective.y.clone()).is_err());
+        assert!(SparseObjective::new(x, objective.y.clone()).is_ok());
+    }
+}
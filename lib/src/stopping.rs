@@ -0,0 +1,82 @@
+use argmin::core::{Error, Problem, Solver, State, TerminationReason, TerminationStatus, KV};
+use nalgebra::base::DVector;
+
+use std::time::{Duration, Instant};
+
+/// The `SolverExit` text of a fit stopped by `Deadline`
+pub(crate) const TIMED_OUT: &str = "Wall-clock budget exhausted";
+/// The `SolverExit` text of a fit stopped by its `Monitor`
+pub(crate) const EARLY_STOPPED: &str = "Validation score stopped improving";
+
+/// Sees the current param once per iteration; `true` stops the fit
+pub(crate) type Monitor<'m> = &'m mut dyn FnMut(&DVector<f64>) -> bool;
+
+///
+/// Stopping criteria argmin's executor lacks, layered over any solver: the
+/// wall-clock budget and an optional `Monitor`. The inner solver's own criteria
+/// (and `max_iters`) still apply.
+///
+pub(crate) struct Deadline<'m, S> {
+    inner: S,
+    deadline: Option<Instant>,
+    monitor: Option<Monitor<'m>>,
+}
+
+impl<'m, S> Deadline<'m, S> {
+    /// `max_duration` from now; `None` never expires
+    pub(crate) fn new(
+        inner: S,
+        max_duration: Option<Duration>,
+        monitor: Option<Monitor<'m>>,
+    ) -> Self {
+        Deadline {
+            inner,
+            deadline: max_duration.map(|d| Instant::now() + d),
+            monitor,
+        }
+    }
+}
+
+/// Has the budget started at `start` run out
+pub(crate) fn expired(start: Instant, max_duration: Option<Duration>) -> bool {
+    max_duration.is_some_and(|d| start.elapsed() >= d)
+}
+
+impl<O, I, S> Solver<O, I> for Deadline<'_, S>
+where
+    I: State<Param = DVector<f64>>,
+    S: Solver<O, I>,
+{
+    const NAME: &'static str = S::NAME;
+
+    fn init(&mut self, problem: &mut Problem<O>, state: I) -> Result<(I, Option<KV>), Error> {
+        self.inner.init(problem, state)
+    }
+
+    fn next_iter(&mut self, problem: &mut Problem<O>, state: I) -> Result<(I, Option<KV>), Error> {
+        self.inner.next_iter(problem, state)
+    }
+
+    fn terminate(&mut self, state: &I) -> TerminationStatus {
+        let status = self.inner.terminate(state);
+        if status.terminated() {
+            return status;
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return TerminationStatus::Terminated(TerminationReason::SolverExit(
+                    TIMED_OUT.to_string(),
+                ));
+            }
+        }
+        let stop = match (&mut self.monitor, state.get_param()) {
+            (Some(monitor), Some(param)) => monitor(param),
+            _ => false,
+        };
+        if stop {
+            TerminationStatus::Terminated(TerminationReason::SolverExit(EARLY_STOPPED.to_string()))
+        } else {
+            TerminationStatus::NotTerminated
+        }
+    }
+}
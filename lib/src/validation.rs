@@ -0,0 +1,309 @@
+use color_eyre::eyre::{eyre, Result};
+use nalgebra::base::DVector;
+
+use std::collections::BTreeMap;
+
+use crate::auc_score::auc_score;
+use crate::configurations::Cfg;
+use crate::logit;
+use crate::models::{Findings, Objective, Prediction};
+
+///
+/// k-fold cross validation for clustered records: every row sharing a group id
+/// (e.g. a customer) lands in the same test fold, so no group is both trained
+/// and scored on. Groups are dealt largest first to the smallest fold, keeping
+/// the folds close in size. Returns the test AUC of each fold.
+///
+pub fn group_cross_validate(
+    objective: &Objective,
+    groups: &[usize],
+    cfg: &Cfg,
+    k: usize,
+) -> Result<Vec<f64>> {
+    let n = objective.y.len();
+    if groups.len() != n {
+        return Err(eyre!("Got {} group ids for {} records", groups.len(), n));
+    }
+    cross_validate_folds(objective, group_folds(groups, k)?, cfg)
+}
+
+/// The rows of each of the `k` folds, every group whole and the largest dealt first
+fn group_folds(groups: &[usize], k: usize) -> Result<Vec<Vec<usize>>> {
+    let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (i, &g) in groups.iter().enumerate() {
+        members.entry(g).or_default().push(i);
+    }
+    if k < 2 || k > members.len() {
+        return Err(eyre!(
+            "k must be between 2 and the number of groups ({}); got {}",
+            members.len(),
+            k
+        ));
+    }
+
+    let mut by_size: Vec<Vec<usize>> = members.into_values().collect();
+    by_size.sort_by_key(|rows| std::cmp::Reverse(rows.len()));
+    let mut folds: Vec<Vec<usize>> = vec![Vec::new(); k];
+    for rows in by_size {
+        let smallest = (0..k).min_by_key(|&f| folds[f].len()).unwrap_or(0);
+        folds[smallest].extend(rows);
+    }
+    Ok(folds)
+}
+
+/// Fit on all but each fold in turn and score the AUC on the fold
+fn cross_validate_folds(
+    objective: &Objective,
+    mut folds: Vec<Vec<usize>>,
+    cfg: &Cfg,
+) -> Result<Vec<f64>> {
+    let n = objective.y.len();
+    let mut fold_of = vec![0; n];
+    for (f, rows) in folds.iter_mut().enumerate() {
+        rows.sort_unstable();
+        for &i in rows.iter() {
+            fold_of[i] = f;
+        }
+    }
+
+    folds
+        .iter()
+        .enumerate()
+        .map(|(f, test_rows)| {
+            let train_rows: Vec<usize> = (0..n).filter(|&i| fold_of[i] != f).collect();
+            let train = objective.subset(&train_rows);
+            let test = objective.subset(test_rows);
+            let findings = train.fit(cfg.clone())?;
+            // the held out fold keeps its offset
+            let probabilities = cfg.link.inverse_vec(&test.log_odds(&findings.all_betas));
+            auc_score(&test.y, &probabilities)
+                .map_err(|e| eyre!("Fold {} can't be scored: {}", f, e))
+        })
+        .collect()
+}
+
+///
+/// Fit on `train` while scoring the AUC on `valid` after every iteration (epoch
+/// for sgd); the fit stops once `patience` checks in a row fail to beat the best
+/// AUC so far. The findings carry the best-AUC betas, not the last ones, and
+/// `termination` records the early stop. `cfg.max_iters` still caps the fit.
+///
+pub fn fit_with_validation<'a>(
+    train: &'a Objective,
+    valid: &Objective,
+    cfg: &Cfg,
+    patience: usize,
+) -> Result<Findings<'a>> {
+    if patience == 0 {
+        return Err(eyre!("The patience must be at least 1"));
+    }
+    if valid.feature_count() != train.feature_count() {
+        return Err(eyre!(
+            "The validation set has {} columns; the training set has {}",
+            valid.feature_count(),
+            train.feature_count()
+        ));
+    }
+    // fails early when the validation set lacks a class
+    auc_score(&valid.y, &DVector::zeros(valid.y.len()))
+        .map_err(|e| eyre!("The validation set can't be scored: {}", e))?;
+
+    let mut best: Option<(f64, DVector<f64>)> = None;
+    let mut stale = 0;
+    let mut monitor = |param: &DVector<f64>| {
+        let probabilities = cfg.link.inverse_vec(&valid.log_odds(param));
+        let auc = auc_score(&valid.y, &probabilities).unwrap_or(f64::NEG_INFINITY);
+        match &best {
+            Some((best_auc, _)) if auc <= *best_auc => {
+                stale += 1;
+                stale >= patience
+            }
+            _ => {
+                best = Some((auc, param.clone()));
+                stale = 0;
+                false
+            }
+        }
+    };
+    let mut findings = logit::run_monitored(train, cfg.clone(), Some(&mut monitor))?;
+
+    if let Some((_, betas)) = best {
+        let p = betas.len();
+        findings.coefficients = betas.rows(0, p - 1).into_owned();
+        findings.intercept = betas[p - 1];
+        findings.final_nll =
+            logit::neg_log_likelihood(&train.log_odds(&betas), &train.y, train.weights.as_ref());
+        findings.default_prediction = findings.cfg.cfg_predict.as_ref().map(|cfg_predict| {
+            let probabilities = findings.cfg.link.inverse_vec(&train.log_odds(&betas));
+            Prediction::from_cfg(probabilities, cfg_predict)
+        });
+        findings.gradient_norm = logit::gradient_norm(train, &findings.cfg, &betas)?;
+        findings.all_betas = betas;
+    }
+    Ok(findings)
+}
+
+///
+/// Recursive feature elimination: fit, drop the predictor with the smallest
+/// standardized coefficient `|beta * std|` (see `feature_importance`), and
+/// refit until `n_features_to_keep` remain. Returns the surviving predictor
+/// indices of `objective`, in their original order.
+///
+pub fn rfe(objective: &Objective, cfg: &Cfg, n_features_to_keep: usize) -> Result<Vec<usize>> {
+    let p = objective.predictor_count();
+    if n_features_to_keep == 0 || n_features_to_keep > p {
+        return Err(eyre!(
+            "Can keep between 1 and {} predictors; got {}",
+            p,
+            n_features_to_keep
+        ));
+    }
+
+    let mut active: Vec<usize> = (0..p).collect();
+    while active.len() > n_features_to_keep {
+        let subset = objective.subset_features(&active)?;
+        let findings = subset.fit(cfg.clone())?;
+        let weakest = subset
+            .column_stats()
+            .iter()
+            .zip(findings.coefficients.iter())
+            .map(|((_, std), beta)| (beta * std).abs())
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(position, _)| position)
+            .ok_or_else(|| eyre!("No predictor left to eliminate"))?;
+        active.remove(weakest);
+    }
+    Ok(active)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::CfgBuilder;
+    use crate::logit::tests::toy;
+    use crate::models::sigmoid_f64;
+    use nalgebra::base::DMatrix;
+
+    #[test]
+    fn group_folds_keep_each_group_whole_and_balance_the_sizes() {
+        // groups of sizes 4, 3, 2, 1
+        let groups = [0, 0, 0, 0, 1, 1, 1, 2, 2, 3];
+        let folds = group_folds(&groups, 2).unwrap();
+        assert_eq!(folds, [vec![0, 1, 2, 3, 9], vec![4, 5, 6, 7, 8]]);
+
+        let err = group_folds(&groups, 5).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "k must be between 2 and the number of groups (4); got 5"
+        );
+        assert!(group_folds(&groups, 1).is_err());
+    }
+
+    #[test]
+    fn group_cross_validate_scores_each_held_out_group_fold() {
+        let objective = toy();
+        let cfg = CfgBuilder::new().build();
+        let groups: Vec<usize> = (0..20).map(|i| i / 2).collect();
+        let aucs = group_cross_validate(&objective, &groups, &cfg, 2).unwrap();
+        assert_eq!(aucs.len(), 2);
+
+        // equal sized groups alternate between the folds
+        let even: Vec<usize> = (0..20).filter(|i| (i / 2) % 2 == 0).collect();
+        let odd: Vec<usize> = (0..20).filter(|i| (i / 2) % 2 == 1).collect();
+        let train = objective.subset(&odd);
+        let test = objective.subset(&even);
+        let findings = train.fit(cfg.clone()).unwrap();
+        let expected =
+            auc_score(&test.y, &findings.predict_proba_on(&test.x).unwrap().inner).unwrap();
+        assert_eq!(aucs[0], expected);
+        assert!(aucs.iter().all(|auc| *auc > 0.5));
+
+        let err = group_cross_validate(&objective, &groups[1..], &cfg, 2).unwrap_err();
+        assert_eq!(err.to_string(), "Got 19 group ids for 20 records");
+    }
+
+    #[test]
+    fn group_cross_validate_scores_the_held_out_fold_with_its_offset() {
+        let offset = DVector::from_fn(20, |i, _| [2.0, -1.0, 0.0][i % 3]);
+        let objective = toy().with_offset(offset).unwrap();
+        let cfg = CfgBuilder::new().build();
+        let groups: Vec<usize> = (0..20).map(|i| i / 2).collect();
+        let aucs = group_cross_validate(&objective, &groups, &cfg, 2).unwrap();
+
+        let even: Vec<usize> = (0..20).filter(|i| (i / 2) % 2 == 0).collect();
+        let odd: Vec<usize> = (0..20).filter(|i| (i / 2) % 2 == 1).collect();
+        let train = objective.subset(&odd);
+        let test = objective.subset(&even);
+        let findings = train.fit(cfg.clone()).unwrap();
+        let log_odds = test.log_odds(&findings.all_betas);
+        assert_eq!(
+            aucs[0],
+            auc_score(&test.y, &log_odds.map(sigmoid_f64)).unwrap()
+        );
+        // scoring without the offset ranks the fold differently
+        let without = findings.predict_proba_on(&test.x).unwrap();
+        assert_ne!(aucs[0], auc_score(&test.y, &without.inner).unwrap());
+    }
+
+    #[test]
+    fn fit_with_validation_stops_once_the_validation_auc_stalls() {
+        let train = toy();
+        let valid = toy().subset(&[0, 3, 9, 12, 15, 16]);
+        let cfg = CfgBuilder::new().build();
+        let full = train.fit(cfg.clone()).unwrap();
+        // one predictor: the ranking, hence the AUC, is fixed after one step
+        let findings = fit_with_validation(&train, &valid, &cfg, 1).unwrap();
+        assert_eq!(
+            findings.termination,
+            argmin::core::TerminationReason::SolverExit(crate::stopping::EARLY_STOPPED.to_string())
+        );
+        assert!(findings.iterations < full.iterations);
+        assert!(findings.final_nll > full.final_nll);
+        let p = findings.all_betas.len();
+        assert_eq!(findings.intercept, findings.all_betas[p - 1]);
+        assert_eq!(
+            findings.final_nll,
+            logit::neg_log_likelihood(&train.log_odds(&findings.all_betas), &train.y, None)
+        );
+    }
+
+    #[test]
+    fn fit_with_validation_checks_its_inputs() {
+        let train = toy();
+        let cfg = CfgBuilder::new().build();
+        let err = fit_with_validation(&train, &toy(), &cfg, 0).unwrap_err();
+        assert_eq!(err.to_string(), "The patience must be at least 1");
+        let one_class = toy().subset(&[0, 1, 2]);
+        assert!(fit_with_validation(&train, &one_class, &cfg, 2).is_err());
+        let wide = toy().expand_features(2, false);
+        let err = fit_with_validation(&train, &wide, &cfg, 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The validation set has 3 columns; the training set has 2"
+        );
+    }
+
+    #[test]
+    fn rfe_drops_the_predictor_with_the_weakest_standardized_effect() {
+        // noise first, then toy's informative predictor, then noise again
+        let base = toy();
+        let x = DMatrix::from_fn(20, 4, |i, j| match j {
+            0 => ((i * 7) % 5) as f64,
+            1 => base.x[(i, 0)],
+            2 => ((i * 3) % 4) as f64 / 10.0,
+            _ => 1.0,
+        });
+        let objective = Objective::new(x, base.y);
+        let cfg = CfgBuilder::new().build();
+        assert_eq!(rfe(&objective, &cfg, 3).unwrap(), [0, 1, 2]);
+        assert_eq!(rfe(&objective, &cfg, 1).unwrap(), [1]);
+
+        let err = rfe(&objective, &cfg, 0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can keep between 1 and 3 predictors; got 0"
+        );
+        assert!(rfe(&objective, &cfg, 4).is_err());
+    }
+}